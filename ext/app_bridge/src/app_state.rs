@@ -1,8 +1,12 @@
-use crate::component::{v3, v4, v4_1};
-use crate::component::v4::standout::app::http::Request;
-use reqwest::blocking::Client;
+use crate::component::{v3, v4, v4_1, v4_2};
+use crate::http_log::HttpLogEntry;
+use crate::http_options::HttpOptions;
+use crate::limits::StoreLimiter;
+use crate::request_builder::BuilderState;
+use crate::transport::{ReqwestTransport, Transport};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use wasmtime::component::ResourceTable;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
 use wasmtime_wasi_io::IoView;
@@ -10,23 +14,38 @@ use wasmtime_wasi_io::IoView;
 pub struct AppState {
     ctx: WasiCtx,
     table: ResourceTable,
-    pub client: Arc<Mutex<Client>>,
-    pub request_list: HashMap<u32, Request>,
-    pub request_body_bytes: HashMap<u32, Vec<u8>>,
+    pub transport: Arc<dyn Transport>,
+    pub request_builders: HashMap<u32, BuilderState>,
     pub next_request_id: u32,
     pub environment_variables: HashMap<String, String>,
+    pub limiter: StoreLimiter,
+    pub http: HttpOptions,
+    /// When the running invocation times out, if it has a deadline.
+    pub deadline: Option<Instant>,
+    /// Connection of the running invocation, which responses are cached for.
+    pub connection_id: Option<String>,
+    /// Requests made since the log was last taken.
+    pub http_log: Mutex<Vec<HttpLogEntry>>,
 }
 
 impl AppState {
-    pub fn new(ctx: WasiCtx, env_vars: Option<HashMap<String, String>>) -> Self {
+    pub fn new(ctx: WasiCtx, env_vars: Option<HashMap<String, String>>, http: HttpOptions) -> Self {
         Self {
             ctx,
             table: ResourceTable::new(),
-            client: Arc::new(Mutex::new(Client::new())),
-            request_list: HashMap::new(),
-            request_body_bytes: HashMap::new(),
+            // Apps build their client once, when their options are parsed.
+            // Stores created without one, as in tests, get their own.
+            transport: http.transport.clone().unwrap_or_else(|| {
+                Arc::new(ReqwestTransport::new(&http.egress, &http.client).expect("failed to build HTTP client"))
+            }),
+            request_builders: HashMap::new(),
             next_request_id: 0,
             environment_variables: env_vars.unwrap_or_default(),
+            limiter: StoreLimiter::default(),
+            http,
+            deadline: None,
+            connection_id: None,
+            http_log: Mutex::new(Vec::new()),
         }
     }
 }
@@ -35,12 +54,11 @@ impl AppState {
 // Macro to implement identical Host traits for multiple WIT versions
 // ============================================================================
 
-/// Implements http::Host and environment::Host for a given WIT version module.
-/// These implementations are identical across versions.
+/// Implements environment::Host for a given WIT version module. The
+/// implementations are identical across versions; http::Host lives with the
+/// request builder.
 macro_rules! impl_host_for_version {
     ($version:ident) => {
-        impl $version::standout::app::http::Host for AppState {}
-
         impl $version::standout::app::environment::Host for AppState {
             fn env_vars(&mut self) -> Vec<(String, String)> {
                 self.environment_variables.clone().into_iter().collect()
@@ -57,6 +75,7 @@ macro_rules! impl_host_for_version {
 impl_host_for_version!(v3);
 impl_host_for_version!(v4);
 impl_host_for_version!(v4_1);
+impl_host_for_version!(v4_2);
 
 // ============================================================================
 // WASI implementations
@@ -79,6 +98,6 @@ impl IoView for AppState {
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new(WasiCtxBuilder::new().build(), None)
+        Self::new(WasiCtxBuilder::new().build(), None, HttpOptions::default())
     }
 }
@@ -0,0 +1,132 @@
+//! On-disk cache of precompiled components.
+//!
+//! Compiling a component with Cranelift is by far the most expensive part of
+//! creating an app. The compiled artifact only depends on the wasm bytes, the
+//! wasmtime version and the engine configuration, so it can be serialized once
+//! and loaded with `Component::deserialize_file` on every following boot.
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use wasmtime::component::Component;
+use wasmtime::{Engine, Result};
+
+const ARTIFACT_EXTENSION: &str = "cwasm";
+
+/// Adapter that lets `Hash` implementations feed a SHA-256 digest.
+struct DigestHasher(Sha256);
+
+impl Hasher for DigestHasher {
+    fn finish(&self) -> u64 {
+        unreachable!("DigestHasher is only used to feed the digest")
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+/// Computes the cache key for a wasm binary compiled by `engine`.
+///
+/// The key covers the wasm bytes and the engine's compatibility hash, which
+/// includes the wasmtime version, the target and all codegen settings.
+pub fn cache_key(engine: &Engine, wasm: &[u8]) -> String {
+    let mut hasher = DigestHasher(Sha256::new());
+    hasher.write(wasm);
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+
+    hasher
+        .0
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Returns the path of the precompiled artifact for the given key.
+pub fn artifact_path(cache_dir: &Path, key: &str) -> PathBuf {
+    cache_dir.join(format!("{}.{}", key, ARTIFACT_EXTENSION))
+}
+
+/// Compiles the component at `file_path` and stores the artifact in `cache_dir`.
+/// Returns the path of the written artifact.
+pub fn precompile(engine: &Engine, file_path: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let wasm = fs::read(file_path)?;
+    let path = artifact_path(cache_dir, &cache_key(engine, &wasm));
+
+    if !path.exists() {
+        let artifact = engine.precompile_component(&wasm)?;
+        write_artifact(cache_dir, &path, &artifact)?;
+    }
+
+    Ok(path)
+}
+
+/// Loads the component from `wasm`, using the precompiled artifact in
+/// `cache_dir` when present and populating the cache when it is not.
+/// `key` must be the `cache_key` of `wasm` for this engine.
+pub fn load_component(engine: &Engine, wasm: &[u8], key: &str, cache_dir: Option<&Path>) -> Result<Component> {
+    let Some(cache_dir) = cache_dir else {
+        return Component::new(engine, wasm);
+    };
+
+    let path = artifact_path(cache_dir, key);
+
+    if path.exists() {
+        // SAFETY: artifacts are only ever written by `write_artifact` from the
+        // output of `Engine::precompile_component`, and the key guarantees
+        // that it was produced by a compatible engine.
+        if let Ok(component) = unsafe { Component::deserialize_file(engine, &path) } {
+            return Ok(component);
+        }
+    }
+
+    // Missing or unreadable artifact: compile and (re)write it.
+    let artifact = engine.precompile_component(wasm)?;
+    write_artifact(cache_dir, &path, &artifact)?;
+
+    // SAFETY: the bytes were just produced by `precompile_component` on this engine.
+    unsafe { Component::deserialize(engine, &artifact) }
+}
+
+/// Writes the artifact atomically so concurrent workers never observe a
+/// partially written file.
+fn write_artifact(cache_dir: &Path, path: &Path, artifact: &[u8]) -> Result<()> {
+    fs::create_dir_all(cache_dir)?;
+
+    let tmp_path = path.with_extension(format!("{}.{}.tmp", ARTIFACT_EXTENSION, std::process::id()));
+    fs::write(&tmp_path, artifact)?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_depends_on_wasm_bytes() {
+        let engine = Engine::default();
+
+        assert_eq!(cache_key(&engine, b"a"), cache_key(&engine, b"a"));
+        assert_ne!(cache_key(&engine, b"a"), cache_key(&engine, b"b"));
+    }
+
+    #[test]
+    fn cache_key_depends_on_engine_config() {
+        let default_engine = Engine::default();
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let fuel_engine = Engine::new(&config).unwrap();
+
+        assert_ne!(cache_key(&default_engine, b"a"), cache_key(&fuel_engine, b"a"));
+    }
+
+    #[test]
+    fn artifact_path_uses_key_as_file_name() {
+        let path = artifact_path(Path::new("/tmp/cache"), "abc");
+        assert_eq!(path, PathBuf::from("/tmp/cache/abc.cwasm"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Sending a batch of requests at the same time.
+//!
+//! `http.send-all` hands the host a list of request builders. Their requests
+//! are sent on scoped threads, as many at a time as the component asks for
+//! and the app allows, and the results are returned in the order of the
+//! builders. With a transport that must be called on the invoking thread, the
+//! batch is sent one request after the other.
+
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// Maps `items` with `f` on up to `parallelism` threads. The results are in
+/// the order of the items.
+pub fn map_concurrently<T, R, F>(items: Vec<T>, parallelism: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let count = items.len();
+    let workers = parallelism.min(count);
+    if workers <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let mut results: Vec<Option<R>> = (0..count).map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                        let Some((index, item)) = next else {
+                            return done;
+                        };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let done = handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every item is mapped"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_the_order_and_the_parallelism_limit() {
+        let running = AtomicUsize::new(0);
+        let most_running = AtomicUsize::new(0);
+
+        let results = map_concurrently((0..12).collect(), 3, |n: u64| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most_running.fetch_max(now, Ordering::SeqCst);
+            // Later items finish first, so the results arrive out of order.
+            thread::sleep(Duration::from_millis(24 - 2 * n));
+            running.fetch_sub(1, Ordering::SeqCst);
+            n * 10
+        });
+
+        assert_eq!(results, (0..12).map(|n| n * 10).collect::<Vec<_>>());
+        assert!(most_running.load(Ordering::SeqCst) <= 3);
+        assert!(most_running.load(Ordering::SeqCst) > 1);
+    }
+}
@@ -0,0 +1,243 @@
+//! Recording and replaying the HTTP traffic of a component.
+//!
+//! In record mode every request that goes out through the http and file
+//! interfaces is written to a JSON cassette together with its response. In
+//! replay mode nothing goes out: each request is answered from the cassette,
+//! and a request that wasn't recorded fails. Requests match a recorded
+//! interaction on method, URL and body, and every interaction is played back
+//! once, in the order it was recorded.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Whether a cassette records live traffic or replays it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// A request as stored in a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    pub body: RecordedBody,
+}
+
+/// A response as stored in a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    pub body: RecordedBody,
+}
+
+/// A body, kept readable in the cassette unless it isn't UTF-8.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedBody {
+    #[serde(rename = "body")]
+    Text(String),
+    #[serde(rename = "body_base64")]
+    Base64(String),
+}
+
+impl Default for RecordedBody {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl RecordedBody {
+    pub fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Base64(BASE64.encode(bytes)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Text(text) => Ok(text.as_bytes().to_vec()),
+            Self::Base64(encoded) => BASE64
+                .decode(encoded)
+                .map_err(|e| format!("Invalid base64 body in HTTP cassette: {}", e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction has been played back.
+    played: Vec<bool>,
+}
+
+/// A cassette shared by every store of an app.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// Opens the cassette at `path`. Replaying reads the recorded
+    /// interactions; recording starts a new cassette, replacing the file on
+    /// the first recorded request.
+    pub fn open(path: impl Into<PathBuf>, mode: CassetteMode) -> Result<Self, String> {
+        let path = path.into();
+
+        let interactions = match mode {
+            CassetteMode::Replay => read_cassette(&path)?.interactions,
+            CassetteMode::Record => Vec::new(),
+        };
+
+        Ok(Self {
+            state: Mutex::new(CassetteState {
+                played: vec![false; interactions.len()],
+                interactions,
+            }),
+            path,
+            mode,
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    /// Answers `request` with the first recorded interaction that matches it
+    /// and hasn't been played yet.
+    pub fn replay(&self, request: &RecordedRequest) -> Result<RecordedResponse, String> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let CassetteState { interactions, played } = &mut *state;
+
+        let index = interactions
+            .iter()
+            .zip(played.iter())
+            .position(|(interaction, played)| !played && matches(&interaction.request, request))
+            .ok_or_else(|| {
+                format!(
+                    "No unplayed interaction in HTTP cassette {} matches {} {}",
+                    self.path.display(),
+                    request.method,
+                    request.url
+                )
+            })?;
+
+        played[index] = true;
+        Ok(interactions[index].response.clone())
+    }
+
+    /// Appends an interaction and writes the cassette.
+    pub fn record(&self, request: RecordedRequest, response: RecordedResponse) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.interactions.push(Interaction { request, response });
+
+        let file = CassetteFile {
+            interactions: state.interactions.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize HTTP cassette: {}", e))?;
+
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write HTTP cassette {}: {}", self.path.display(), e))
+    }
+}
+
+fn read_cassette(path: &Path) -> Result<CassetteFile, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read HTTP cassette {}: {}", path.display(), e))?;
+
+    serde_json::from_str(&json).map_err(|e| format!("Invalid HTTP cassette {}: {}", path.display(), e))
+}
+
+fn matches(recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+    recorded.method.eq_ignore_ascii_case(&request.method)
+        && recorded.url == request.url
+        && recorded.body == request.body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, body: &[u8]) -> RecordedRequest {
+        RecordedRequest {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: RecordedBody::new(body),
+        }
+    }
+
+    fn response(status: u16, body: &[u8]) -> RecordedResponse {
+        RecordedResponse {
+            status,
+            headers: Vec::new(),
+            body: RecordedBody::new(body),
+        }
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let path = std::env::temp_dir().join(format!("app_bridge_cassette_{}.json", std::process::id()));
+
+        let recorder = Cassette::open(&path, CassetteMode::Record).unwrap();
+        recorder.record(request("https://example.com/a", b"{}"), response(201, b"created")).unwrap();
+        recorder.record(request("https://example.com/b", b"\xff\x00"), response(200, b"\x89PNG")).unwrap();
+        recorder.record(request("https://example.com/a", b"{}"), response(409, b"conflict")).unwrap();
+
+        let player = Cassette::open(&path, CassetteMode::Replay).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let binary = player.replay(&request("https://example.com/b", b"\xff\x00")).unwrap();
+        assert_eq!(binary.body.to_bytes().unwrap(), b"\x89PNG");
+
+        assert_eq!(player.replay(&request("https://example.com/a", b"{}")).unwrap().status, 201);
+        assert_eq!(player.replay(&request("https://example.com/a", b"{}")).unwrap().status, 409);
+        assert!(player.replay(&request("https://example.com/a", b"{}")).is_err());
+    }
+
+    #[test]
+    fn unrecorded_requests_fail() {
+        let path = std::env::temp_dir().join(format!("app_bridge_cassette_miss_{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"interactions":[{"request":{"method":"GET","url":"https://example.com/","body":""},
+                "response":{"status":200,"body":"ok"}}]}"#,
+        )
+        .unwrap();
+
+        let player = Cassette::open(&path, CassetteMode::Replay).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let error = player.replay(&request("https://example.com/", b"")).unwrap_err();
+        assert!(error.contains("matches POST https://example.com/"), "{error}");
+    }
+
+    #[test]
+    fn replaying_a_missing_cassette_fails() {
+        let error = Cassette::open("/nonexistent/cassette.json", CassetteMode::Replay).unwrap_err();
+        assert!(error.contains("Failed to read HTTP cassette"), "{error}");
+    }
+}
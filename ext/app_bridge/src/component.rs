@@ -1,10 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::result::Result::Ok;
+use std::thread;
+use std::time::{Duration, Instant};
 use wasmtime::component::{Component, HasSelf, Linker};
-use wasmtime::{Engine, Result, Store};
+use wasmtime::{Config, Engine, Result, Store};
 use wasmtime_wasi::WasiCtxBuilder;
 
 use crate::app_state::AppState;
+use crate::http_options::HttpOptions;
+use crate::limits::{ResourceLimits, StoreLimiter};
 use crate::types::{
     ActionContext, ActionResponse, AppError, Connection, ErrorCode, TriggerContext,
     ReferenceObject, TriggerEvent, TriggerResponse,
@@ -16,8 +20,8 @@ use crate::types::{
 // To add a new version:
 // 1. Add a new pub mod vN { bindgen!(...) }
 // 2. Add impl_conversions!(vN) below
-// 3. Add variant to BridgeWrapper enum
-// 4. Add to build_linker() and app() functions
+// 3. Add variant to BridgeWrapper and BridgePreWrapper enums
+// 4. Add to build_linker(), WIT_WORLDS and BridgePreWrapper::new()
 // 5. Add arm to each bridge_method! in BridgeWrapper impl
 // ============================================================================
 
@@ -42,6 +46,13 @@ pub mod v4_1 {
     });
 }
 
+pub mod v4_2 {
+    wasmtime::component::bindgen!({
+        path: "./wit/v4_2",
+        world: "bridge",
+    });
+}
+
 // ============================================================================
 // Version conversion macro - generates From impls for a version module
 // ============================================================================
@@ -86,6 +97,8 @@ macro_rules! impl_conversions {
                 Self {
                     store: r.store,
                     events: r.events.into_iter().map(Into::into).collect(),
+                    fuel_consumed: None,
+                    http_log: Vec::new(),
                 }
             }
         }
@@ -93,7 +106,11 @@ macro_rules! impl_conversions {
         // ActionResponse: version → canonical
         impl From<$v::standout::app::types::ActionResponse> for ActionResponse {
             fn from(r: $v::standout::app::types::ActionResponse) -> Self {
-                Self { serialized_output: r.serialized_output }
+                Self {
+                    serialized_output: r.serialized_output,
+                    fuel_consumed: None,
+                    http_log: Vec::new(),
+                }
             }
         }
 
@@ -165,22 +182,26 @@ macro_rules! impl_action_context_conversion_retry {
     };
 }
 
-impl From<v4_1::standout::app::types::ReferenceObject> for ReferenceObject {
-    fn from(r: v4_1::standout::app::types::ReferenceObject) -> Self {
-        Self {
-            reference: r.reference,
-            status: r.status,
+macro_rules! impl_reference_object_conversion {
+    ($v:ident) => {
+        impl From<$v::standout::app::types::ReferenceObject> for ReferenceObject {
+            fn from(r: $v::standout::app::types::ReferenceObject) -> Self {
+                Self {
+                    reference: r.reference,
+                    status: r.status,
+                }
+            }
         }
-    }
-}
 
-impl From<&ReferenceObject> for v4_1::standout::app::types::ReferenceObject {
-    fn from(r: &ReferenceObject) -> Self {
-        Self {
-            reference: r.reference.clone(),
-            status: r.status.clone(),
+        impl From<&ReferenceObject> for $v::standout::app::types::ReferenceObject {
+            fn from(r: &ReferenceObject) -> Self {
+                Self {
+                    reference: r.reference.clone(),
+                    status: r.status.clone(),
+                }
+            }
         }
-    }
+    };
 }
 
 // Generate conversions for all supported versions
@@ -199,6 +220,15 @@ impl_error_code_conversion!(
 impl_conversions!(v4_1);
 impl_app_error_conversion!(v4_1);
 impl_action_context_conversion_retry!(v4_1);
+impl_reference_object_conversion!(v4_1);
+impl_error_code_conversion!(
+    v4_2,
+    V::RetryWithReference(r) => Self::RetryWithReference(r.into()),
+);
+impl_conversions!(v4_2);
+impl_app_error_conversion!(v4_2);
+impl_action_context_conversion_retry!(v4_2);
+impl_reference_object_conversion!(v4_2);
 
 // ============================================================================
 // BridgeWrapper - unified interface for all component versions
@@ -210,6 +240,7 @@ pub enum BridgeWrapper {
     V3(v3::Bridge),
     V4(v4::Bridge),
     V4_1(v4_1::Bridge),
+    V4_2(v4_2::Bridge),
 }
 
 impl BridgeWrapper {
@@ -219,6 +250,7 @@ impl BridgeWrapper {
             BridgeWrapper::V3(_) => "3.0.0",
             BridgeWrapper::V4(_) => "4.0.0",
             BridgeWrapper::V4_1(_) => "4.1.0",
+            BridgeWrapper::V4_2(_) => "4.2.0",
         }
     }
 }
@@ -242,6 +274,10 @@ macro_rules! bridge_method {
                     let r = b.$interface().$method(store)?;
                     Ok(r.map_err(Into::into))
                 }
+                BridgeWrapper::V4_2(b) => {
+                    let r = b.$interface().$method(store)?;
+                    Ok(r.map_err(Into::into))
+                }
             }
         }
     };
@@ -261,6 +297,10 @@ macro_rules! bridge_method {
                     let r = b.$interface().$method(store, &ctx.into())?;
                     Ok(r.map(Into::into).map_err(Into::into))
                 }
+                BridgeWrapper::V4_2(b) => {
+                    let r = b.$interface().$method(store, &ctx.into())?;
+                    Ok(r.map(Into::into).map_err(Into::into))
+                }
             }
         }
     };
@@ -280,6 +320,10 @@ macro_rules! bridge_method {
                     let r = b.$interface().$method(store, &ctx.into())?;
                     Ok(r.map(Into::into).map_err(Into::into))
                 }
+                BridgeWrapper::V4_2(b) => {
+                    let r = b.$interface().$method(store, &ctx.into())?;
+                    Ok(r.map(Into::into).map_err(Into::into))
+                }
             }
         }
     };
@@ -303,8 +347,76 @@ impl BridgeWrapper {
 // Builder functions
 // ============================================================================
 
-pub fn build_engine() -> Engine {
-    Engine::default()
+/// How often the epoch ticker advances the engine epoch. This is the
+/// granularity of invocation deadlines.
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Epoch delta used when no deadline applies. Far enough in the future to
+/// never be reached, without overflowing when added to the current epoch.
+const NO_DEADLINE: u64 = u64::MAX / 2;
+
+/// Engine settings that can differ between apps. Apps with equal options
+/// share an engine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EngineOptions {
+    /// Meter the instructions executed by components with fuel.
+    pub fuel: bool,
+}
+
+pub fn build_engine(options: EngineOptions) -> Result<Engine> {
+    let mut config = Config::new();
+    // Lets invocation deadlines interrupt components running in wasm code.
+    config.epoch_interruption(true);
+    config.consume_fuel(options.fuel);
+
+    Engine::new(&config)
+}
+
+/// Spawns a thread that advances the engine epoch every `EPOCH_TICK`.
+/// The thread stops once the engine has been dropped.
+pub fn start_epoch_ticker(engine: &Engine) {
+    let engine = engine.weak();
+
+    thread::Builder::new()
+        .name("app_bridge-epoch".to_string())
+        .spawn(move || {
+            while let Some(engine) = engine.upgrade() {
+                engine.increment_epoch();
+                drop(engine);
+                thread::sleep(EPOCH_TICK);
+            }
+        })
+        .expect("failed to spawn epoch ticker thread");
+}
+
+/// Sets the deadline for the next invocation in `store`. Wasm code still
+/// running when the deadline passes traps with `Trap::Interrupt`, and HTTP
+/// requests made by the component are cut off at the same time.
+pub fn set_deadline(store: &mut Store<AppState>, timeout: Option<Duration>) {
+    let ticks = match timeout {
+        Some(timeout) => timeout.as_nanos().div_ceil(EPOCH_TICK.as_nanos()).max(1) as u64,
+        None => NO_DEADLINE,
+    };
+
+    store.set_epoch_deadline(ticks);
+    store.data_mut().deadline = timeout.map(|timeout| Instant::now() + timeout);
+}
+
+/// Sets the fuel budget for the next invocation in `store`. Without a budget
+/// the store gets unlimited fuel. Does nothing when the engine doesn't
+/// meter fuel.
+pub fn set_fuel_budget(store: &mut Store<AppState>, budget: Option<u64>) {
+    // Only fails when fuel metering is disabled, in which case there is
+    // nothing to set.
+    let _ = store.set_fuel(budget.unwrap_or(u64::MAX));
+}
+
+/// Returns the fuel used from `budget` since it was set with `set_fuel_budget`.
+pub fn fuel_consumed(store: &Store<AppState>, budget: Option<u64>) -> Option<u64> {
+    let budget = budget?;
+    let remaining = store.get_fuel().ok()?;
+
+    Some(budget.saturating_sub(remaining))
 }
 
 pub fn build_linker(engine: &Engine) -> Result<Linker<AppState>> {
@@ -328,6 +440,11 @@ pub fn build_linker(engine: &Engine) -> Result<Linker<AppState>> {
     v4_1::standout::app::environment::add_to_linker::<AppState, HasSelf<AppState>>(&mut linker, |s| s)?;
     v4_1::standout::app::file::add_to_linker::<AppState, HasSelf<AppState>>(&mut linker, |s| s)?;
 
+    // v4.2: http (with request timeouts) + environment + file
+    v4_2::standout::app::http::add_to_linker::<AppState, HasSelf<AppState>>(&mut linker, |s| s)?;
+    v4_2::standout::app::environment::add_to_linker::<AppState, HasSelf<AppState>>(&mut linker, |s| s)?;
+    v4_2::standout::app::file::add_to_linker::<AppState, HasSelf<AppState>>(&mut linker, |s| s)?;
+
     // Add new versions here:
     // v5::standout::app::http::add_to_linker(&mut linker, |s| s)?;
     // v5::standout::app::environment::add_to_linker(&mut linker, |s| s)?;
@@ -337,7 +454,12 @@ pub fn build_linker(engine: &Engine) -> Result<Linker<AppState>> {
     Ok(linker)
 }
 
-pub fn build_store(engine: &Engine, env_vars: Option<HashMap<String, String>>) -> Store<AppState> {
+pub fn build_store(
+    engine: &Engine,
+    env_vars: Option<HashMap<String, String>>,
+    limits: ResourceLimits,
+    http: HttpOptions,
+) -> Store<AppState> {
     let mut builder = WasiCtxBuilder::new();
 
     if let Some(env_vars) = &env_vars {
@@ -346,36 +468,365 @@ pub fn build_store(engine: &Engine, env_vars: Option<HashMap<String, String>>) -
         }
     }
 
-    Store::new(engine, AppState::new(builder.build(), env_vars))
+    let mut state = AppState::new(builder.build(), env_vars, http);
+    state.limiter = StoreLimiter::new(limits);
+
+    let mut store = Store::new(engine, state);
+    store.limiter(|state| &mut state.limiter);
+    store.epoch_deadline_trap();
+    set_deadline(&mut store, None);
+    set_fuel_budget(&mut store, None);
+
+    store
 }
 
-/// Try to instantiate a WASM component.
-/// Attempts versions from newest to oldest until one succeeds.
-pub fn app(
-    file_path: String,
-    engine: Engine,
-    store: &mut Store<AppState>,
-    linker: Linker<AppState>,
-) -> Result<BridgeWrapper> {
-    let component = Component::from_file(&engine, &file_path)?;
-
-    // Try versions newest-first. When adding vN, insert at the top.
-    // v4.1 (current - has file interface)
-    if let Ok(instance) = v4_1::Bridge::instantiate(&mut *store, &component, &linker) {
-        return Ok(BridgeWrapper::V4_1(instance));
+// ============================================================================
+// WIT version detection
+//
+// Components built against a WIT version import and export that version's
+// `standout:app` interfaces, named like "standout:app/triggers@4.1.0". The
+// version is read from these names before any bindings are tried.
+//
+// To add vN: insert its world at the top of WIT_WORLDS
+// ============================================================================
+
+const WIT_PACKAGE: &str = "standout:app";
+
+/// The `standout:app` interfaces a WIT version's `bridge` world uses.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WitWorld {
+    pub version: &'static str,
+    imports: &'static [&'static str],
+    exports: &'static [&'static str],
+}
+
+/// Supported WIT versions, newest first.
+const WIT_WORLDS: &[WitWorld] = &[
+    WitWorld {
+        version: "4.2.0",
+        imports: &["http", "environment", "file"],
+        exports: &["triggers", "actions"],
+    },
+    WitWorld {
+        version: "4.1.0",
+        imports: &["http", "environment", "file"],
+        exports: &["triggers", "actions"],
+    },
+    WitWorld {
+        version: "4.0.0",
+        imports: &["http", "environment", "file"],
+        exports: &["triggers", "actions"],
+    },
+    WitWorld {
+        version: "3.0.0",
+        imports: &["http", "environment"],
+        exports: &["triggers", "actions"],
+    },
+];
+
+/// Splits "standout:app/http@4.1.0" into ("http", "4.1.0"). Returns `None`
+/// for interfaces of other packages, such as WASI.
+fn parse_interface_name(name: &str) -> Option<(&str, &str)> {
+    let rest = name.strip_prefix(WIT_PACKAGE)?.strip_prefix('/')?;
+    rest.split_once('@')
+}
+
+/// `standout:app` interfaces of `items`, keyed by interface name.
+fn package_interfaces<'a>(items: impl Iterator<Item = &'a str>) -> BTreeMap<&'a str, &'a str> {
+    items.filter_map(parse_interface_name).collect()
+}
+
+fn describe(interfaces: &BTreeMap<&str, &str>) -> String {
+    interfaces
+        .iter()
+        .map(|(name, version)| format!("{}@{}", name, version))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Determines which supported WIT version `component` was built against.
+/// When it matches none, the error names the missing or mismatched
+/// interfaces.
+pub fn detect_wit_world(engine: &Engine, component: &Component) -> Result<&'static WitWorld> {
+    let component_type = component.component_type();
+    let imports = package_interfaces(component_type.imports(engine).map(|(name, _)| name));
+    let exports = package_interfaces(component_type.exports(engine).map(|(name, _)| name));
+
+    let supported = WIT_WORLDS.iter().map(|world| world.version).collect::<Vec<_>>().join(", ");
+
+    let mut versions: Vec<&str> = exports.values().copied().collect();
+    versions.sort_unstable();
+    versions.dedup();
+    let version = match versions.as_slice() {
+        [version] => *version,
+        [] => {
+            return Err(wasmtime::Error::msg(format!(
+                "Component exports no {} interfaces (expected triggers and actions)",
+                WIT_PACKAGE
+            )))
+        }
+        _ => {
+            return Err(wasmtime::Error::msg(format!(
+                "Component exports interfaces from several {} versions: {}",
+                WIT_PACKAGE,
+                describe(&exports)
+            )))
+        }
+    };
+
+    let Some(world) = WIT_WORLDS.iter().find(|world| world.version == version) else {
+        return Err(wasmtime::Error::msg(format!(
+            "Component targets {}@{}, which is not supported (supported versions: {})",
+            WIT_PACKAGE, version, supported
+        )));
+    };
+
+    let mut problems = Vec::new();
+    for name in world.exports {
+        if !exports.contains_key(name) {
+            problems.push(format!("missing export {}", name));
+        }
+    }
+    for (name, import_version) in &imports {
+        if !world.imports.contains(name) {
+            problems.push(format!("unknown import {}@{}", name, import_version));
+        } else if *import_version != version {
+            problems.push(format!("import {}@{} does not match version {}", name, import_version, version));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(world)
+    } else {
+        Err(wasmtime::Error::msg(format!(
+            "Component does not match {}@{}: {}",
+            WIT_PACKAGE,
+            version,
+            problems.join("; ")
+        )))
+    }
+}
+
+// ============================================================================
+// BridgePreWrapper - a component pre-linked against the shared linker
+//
+// Instantiating from an InstancePre skips import resolution and type checks,
+// so one BridgePreWrapper can cheaply create instances for many stores.
+//
+// To add vN: add variant BridgePreWrapper::VN(vN::BridgePre<AppState>)
+// ============================================================================
+
+pub enum BridgePreWrapper {
+    V3(v3::BridgePre<AppState>),
+    V4(v4::BridgePre<AppState>),
+    V4_1(v4_1::BridgePre<AppState>),
+    V4_2(v4_2::BridgePre<AppState>),
+}
+
+impl BridgePreWrapper {
+    /// Pre-instantiate a WASM component with the bindings of the WIT version
+    /// it was built against.
+    pub fn new(linker: &Linker<AppState>, component: &Component) -> Result<Self> {
+        let world = detect_wit_world(linker.engine(), component)?;
+        let instance_pre = linker.instantiate_pre(component)?;
+
+        let mismatch = |e: wasmtime::Error| {
+            e.context(format!(
+                "Component uses {}@{} but its interfaces don't match the expected types",
+                WIT_PACKAGE, world.version
+            ))
+        };
+
+        // When adding vN, add an arm here.
+        match world.version {
+            "4.2.0" => Ok(BridgePreWrapper::V4_2(v4_2::BridgePre::new(instance_pre).map_err(mismatch)?)),
+            "4.1.0" => Ok(BridgePreWrapper::V4_1(v4_1::BridgePre::new(instance_pre).map_err(mismatch)?)),
+            "4.0.0" => Ok(BridgePreWrapper::V4(v4::BridgePre::new(instance_pre).map_err(mismatch)?)),
+            "3.0.0" => Ok(BridgePreWrapper::V3(v3::BridgePre::new(instance_pre).map_err(mismatch)?)),
+            version => unreachable!("WIT_WORLDS lists {} without bindings", version),
+        }
+    }
+
+    /// Create a new instance of the component in `store`.
+    pub fn instantiate(&self, store: &mut Store<AppState>) -> Result<BridgeWrapper> {
+        match self {
+            BridgePreWrapper::V3(pre) => Ok(BridgeWrapper::V3(pre.instantiate(store)?)),
+            BridgePreWrapper::V4(pre) => Ok(BridgeWrapper::V4(pre.instantiate(store)?)),
+            BridgePreWrapper::V4_1(pre) => Ok(BridgeWrapper::V4_1(pre.instantiate(store)?)),
+            BridgePreWrapper::V4_2(pre) => Ok(BridgeWrapper::V4_2(pre.instantiate(store)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::ResourceLimitExceeded;
+    use wasmtime::{Instance, Module, Trap};
+
+    /// Builds a component importing and exporting empty instances under the
+    /// given interface names.
+    fn component_with(imports: &[&str], exports: &[&str]) -> (Engine, Component) {
+        let engine = Engine::default();
+        let imports: String = imports.iter().map(|name| format!("(import \"{}\" (instance))", name)).collect();
+        let exports: String = exports
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("(instance $e{} ) (export \"{}\" (instance $e{}))", i, name, i))
+            .collect();
+        let component = Component::new(&engine, format!("(component {} {})", imports, exports)).unwrap();
+
+        (engine, component)
+    }
+
+    #[test]
+    fn detects_wit_version_from_interface_names() {
+        let (engine, component) = component_with(
+            &["standout:app/http@4.0.0", "wasi:cli/environment@0.2.0"],
+            &["standout:app/triggers@4.0.0", "standout:app/actions@4.0.0"],
+        );
+
+        assert_eq!(detect_wit_world(&engine, &component).unwrap().version, "4.0.0");
+    }
+
+    #[test]
+    fn detects_wit_4_2() {
+        let (engine, component) = component_with(
+            &["standout:app/http@4.2.0", "standout:app/environment@4.2.0", "standout:app/file@4.2.0"],
+            &["standout:app/triggers@4.2.0", "standout:app/actions@4.2.0"],
+        );
+
+        assert_eq!(detect_wit_world(&engine, &component).unwrap().version, "4.2.0");
     }
 
-    // v4
-    if let Ok(instance) = v4::Bridge::instantiate(&mut *store, &component, &linker) {
-        return Ok(BridgeWrapper::V4(instance));
+    #[test]
+    fn reports_missing_and_mismatched_interfaces() {
+        let (engine, component) = component_with(
+            &["standout:app/http@4.0.0", "standout:app/files@4.1.0"],
+            &["standout:app/triggers@4.1.0"],
+        );
+
+        let err = detect_wit_world(&engine, &component).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Component does not match standout:app@4.1.0: missing export actions; \
+             unknown import files@4.1.0; import http@4.0.0 does not match version 4.1.0"
+        );
     }
 
-    // v3 (legacy - no file interface)
-    if let Ok(instance) = v3::Bridge::instantiate(&mut *store, &component, &linker) {
-        return Ok(BridgeWrapper::V3(instance));
+    #[test]
+    fn reports_unsupported_wit_version() {
+        let (engine, component) =
+            component_with(&[], &["standout:app/triggers@9.0.0", "standout:app/actions@9.0.0"]);
+
+        let err = detect_wit_world(&engine, &component).unwrap_err().to_string();
+        assert!(err.starts_with("Component targets standout:app@9.0.0, which is not supported"), "{}", err);
+    }
+
+    #[test]
+    fn reports_component_without_bridge_exports() {
+        let (engine, component) = component_with(&[], &[]);
+
+        let err = detect_wit_world(&engine, &component).unwrap_err().to_string();
+        assert!(err.starts_with("Component exports no standout:app interfaces"), "{}", err);
+    }
+
+    #[test]
+    fn deadline_interrupts_running_wasm() {
+        let engine = build_engine(EngineOptions::default()).unwrap();
+        start_epoch_ticker(&engine);
+
+        let module = Module::new(&engine, r#"(module (func (export "spin") (loop (br 0))))"#).unwrap();
+        let mut store = build_store(&engine, None, ResourceLimits::default(), HttpOptions::default());
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let spin = instance.get_typed_func::<(), ()>(&mut store, "spin").unwrap();
+
+        set_deadline(&mut store, Some(Duration::from_millis(20)));
+        let err = spin.call(&mut store, ()).unwrap_err();
+
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::Interrupt));
+    }
+
+    #[test]
+    fn fuel_budget_limits_and_measures_execution() {
+        let engine = build_engine(EngineOptions { fuel: true }).unwrap();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (func (export "count") (param i32)
+                    (loop
+                        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                        (br_if 0 (local.get 0)))))"#,
+        )
+        .unwrap();
+        let mut store = build_store(&engine, None, ResourceLimits::default(), HttpOptions::default());
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let count = instance.get_typed_func::<i32, ()>(&mut store, "count").unwrap();
+
+        set_fuel_budget(&mut store, Some(10_000));
+        count.call(&mut store, 10).unwrap();
+        let consumed = fuel_consumed(&store, Some(10_000)).unwrap();
+        assert!(consumed > 0 && consumed < 10_000);
+
+        set_fuel_budget(&mut store, Some(10_000));
+        let err = count.call(&mut store, 100_000).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::OutOfFuel));
     }
 
-    Err(wasmtime::Error::msg(
-        "Failed to instantiate component: no compatible WIT version found (tried v4.1, v4, v3)",
-    ))
+    #[test]
+    fn memory_limit_fails_growth_past_limit() {
+        let engine = build_engine(EngineOptions::default()).unwrap();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (memory 1)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0))))"#,
+        )
+        .unwrap();
+        let limits = ResourceLimits {
+            max_memory: Some(2 * 65536),
+            ..Default::default()
+        };
+        let mut store = build_store(&engine, None, limits, HttpOptions::default());
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let grow = instance.get_typed_func::<i32, i32>(&mut store, "grow").unwrap();
+
+        assert_eq!(grow.call(&mut store, 1).unwrap(), 1);
+        assert_eq!(store.data().limiter.memory_size(), 2 * 65536);
+        let err = grow.call(&mut store, 1).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ResourceLimitExceeded>(),
+            Some(ResourceLimitExceeded::Memory { limit: 131072, .. })
+        ));
+    }
+
+    #[test]
+    fn instance_and_table_counts_are_resource_limits() {
+        let engine = build_engine(EngineOptions::default()).unwrap();
+        let module = Module::new(&engine, r#"(module (table 1 funcref) (table 1 funcref))"#).unwrap();
+
+        let limits = ResourceLimits {
+            max_instances: Some(1),
+            ..Default::default()
+        };
+        let mut store = build_store(&engine, None, limits, HttpOptions::default());
+        Instance::new(&mut store, &module, &[]).unwrap();
+        let err = Instance::new(&mut store, &module, &[]).unwrap_err();
+        assert!(matches!(
+            ResourceLimitExceeded::find(&err, &limits),
+            Some(ResourceLimitExceeded::Instances { desired: 2, limit: 1 })
+        ));
+
+        let limits = ResourceLimits {
+            max_tables: Some(1),
+            ..Default::default()
+        };
+        let mut store = build_store(&engine, None, limits, HttpOptions::default());
+        let err = Instance::new(&mut store, &module, &[]).unwrap_err();
+        assert_eq!(
+            ResourceLimitExceeded::find(&err, &limits).unwrap().to_string(),
+            "Table count limit exceeded: tried to create 2 tables, limit is 1 tables"
+        );
+    }
 }
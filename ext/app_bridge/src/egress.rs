@@ -0,0 +1,378 @@
+//! Egress policy for the HTTP requests components make.
+//!
+//! Connectors fetch user-supplied URLs, so the host decides which servers
+//! they may reach. Host names and ports are checked on every URL, including
+//! redirect targets, and addresses are checked after DNS resolution so a
+//! public name pointing at an internal address is refused as well. Loopback,
+//! private and link-local ranges are blocked unless explicitly allowed.
+
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+/// Which hosts, addresses and ports components may connect to.
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    /// Only these hosts may be reached, when not empty. `*.example.com`
+    /// matches any subdomain of `example.com`.
+    pub allow_hosts: Vec<String>,
+    /// Hosts that may never be reached, in the same format.
+    pub deny_hosts: Vec<String>,
+    /// Address ranges that may be reached even though they are internal.
+    pub allow_cidrs: Vec<IpNet>,
+    /// Address ranges that may never be reached.
+    pub deny_cidrs: Vec<IpNet>,
+    /// Only these ports may be reached, when not empty.
+    pub allow_ports: Vec<u16>,
+    /// Allows loopback, private and link-local addresses.
+    pub allow_private_networks: bool,
+}
+
+/// A request refused by the egress policy.
+#[derive(Debug)]
+pub struct EgressDenied(String);
+
+impl fmt::Display for EgressDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blocked by egress policy: {}", self.0)
+    }
+}
+
+impl std::error::Error for EgressDenied {}
+
+impl EgressPolicy {
+    /// Checks the host and port of `url`, and its address when the host is
+    /// an IP literal (those never reach the resolver).
+    pub fn check_url(&self, url: &Url) -> Result<(), EgressDenied> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| EgressDenied(format!("{} has no host", url)))?;
+
+        // IPv6 literals come bracketed, e.g. "[::1]".
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        self.check_host(host)?;
+
+        if let Some(port) = url.port_or_known_default() {
+            if !self.allow_ports.is_empty() && !self.allow_ports.contains(&port) {
+                return Err(EgressDenied(format!("port {} is not allowed", port)));
+            }
+        }
+
+        match host.parse::<IpAddr>() {
+            Ok(ip) => self.check_ip(host, ip),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn check_host(&self, host: &str) -> Result<(), EgressDenied> {
+        if self.deny_hosts.iter().any(|pattern| host_matches(pattern, host)) {
+            return Err(EgressDenied(format!("host {} is denied", host)));
+        }
+        if !self.allow_hosts.is_empty() && !self.allow_hosts.iter().any(|pattern| host_matches(pattern, host)) {
+            return Err(EgressDenied(format!("host {} is not allowed", host)));
+        }
+        Ok(())
+    }
+
+    /// Checks an address `host` resolved to.
+    pub fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), EgressDenied> {
+        let ip = canonical(ip);
+
+        if self.deny_cidrs.iter().any(|net| net.contains(&ip)) {
+            return Err(EgressDenied(format!("{} resolves to denied address {}", host, ip)));
+        }
+        if self.allow_cidrs.iter().any(|net| net.contains(&ip)) {
+            return Ok(());
+        }
+        if is_internal(ip) && !self.allow_private_networks {
+            return Err(EgressDenied(format!("{} resolves to internal address {}", host, ip)));
+        }
+        Ok(())
+    }
+
+    /// Resolves the host of `url` and checks the addresses it resolves to.
+    /// For requests sent through a proxy or a transport that resolves the
+    /// host itself, and so never asks `PolicyResolver`. A host that doesn't
+    /// resolve here is refused, unless the policy couldn't refuse any address
+    /// anyway.
+    pub fn check_resolved(&self, url: &Url) -> Result<(), EgressDenied> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        // IP literals are checked by `check_url`.
+        if (self.allow_private_networks && self.deny_cidrs.is_empty()) || host.parse::<IpAddr>().is_ok() {
+            return Ok(());
+        }
+
+        let addrs = (host, 0)
+            .to_socket_addrs()
+            .map_err(|e| EgressDenied(format!("{} couldn't be resolved to check its address: {}", host, e)))?;
+        for addr in addrs {
+            self.check_ip(host, addr.ip())?;
+        }
+        Ok(())
+    }
+
+    /// Redirect policy that checks every redirect target and follows up to
+    /// `max_redirects` redirects. With 0 redirects aren't followed at all
+    /// and the redirect response is returned. `resolve` also checks the
+    /// addresses of targets, for clients that send through a proxy.
+    pub fn redirect_policy(self: &Arc<Self>, max_redirects: usize, resolve: bool) -> reqwest::redirect::Policy {
+        let policy = Arc::clone(self);
+
+        let check = move |url: &Url| {
+            policy.check_url(url)?;
+            if resolve {
+                policy.check_resolved(url)?;
+            }
+            Ok::<_, EgressDenied>(())
+        };
+
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if max_redirects == 0 {
+                attempt.stop()
+            } else if let Err(denied) = check(attempt.url()) {
+                attempt.error(denied)
+            } else if attempt.previous().len() >= max_redirects {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+}
+
+/// Resolves host names with the system resolver and refuses names that
+/// resolve to any address the policy blocks.
+pub struct PolicyResolver {
+    policy: Arc<EgressPolicy>,
+    /// The host's own proxy, which may well be internal. Requests through it
+    /// are only resolved by the proxy, so its address is the only one seen
+    /// here, and their hosts are checked with `check_resolved` instead.
+    proxy_host: Option<String>,
+}
+
+impl PolicyResolver {
+    pub fn new(policy: Arc<EgressPolicy>, proxy_host: Option<String>) -> Self {
+        Self { policy, proxy_host }
+    }
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = Arc::clone(&self.policy);
+        let host = name.as_str().to_string();
+        let trusted = self
+            .proxy_host
+            .as_ref()
+            .is_some_and(|proxy| proxy.eq_ignore_ascii_case(&host));
+
+        Box::pin(async move {
+            let lookup_host = host.clone();
+            let addrs: Vec<SocketAddr> =
+                tokio::task::spawn_blocking(move || (lookup_host.as_str(), 0).to_socket_addrs())
+                    .await??
+                    .collect();
+
+            if !trusted {
+                for addr in &addrs {
+                    policy.check_ip(&host, addr.ip())?;
+                }
+            }
+
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Finds an `EgressDenied` among the causes of `error`.
+pub fn denied_cause<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a EgressDenied> {
+    let mut source = Some(error);
+    while let Some(cause) = source {
+        if let Some(denied) = cause.downcast_ref::<EgressDenied>() {
+            return Some(denied);
+        }
+        source = cause.source();
+    }
+    None
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.len() > domain.len() + 1 && {
+            let (sub, rest) = host.split_at(host.len() - domain.len());
+            sub.ends_with('.') && rest.eq_ignore_ascii_case(domain)
+        },
+        None => host.eq_ignore_ascii_case(pattern),
+    }
+}
+
+/// Treats IPv6 addresses that embed an IPv4 address as that address, so
+/// they can't be used to reach internal IPv4 hosts.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => embedded_v4(v6).map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// The IPv4 address in an IPv4-mapped (`::ffff:a.b.c.d`), IPv4-compatible
+/// (`::a.b.c.d`), NAT64 (`64:ff9b::a.b.c.d`) or 6to4 (`2002:aabb:ccdd::`)
+/// address.
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = ip.octets();
+    let last = Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]);
+
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, _, _] => Some(last),
+        // :: and ::1 are IPv6's own unspecified and loopback addresses.
+        [0, 0, 0, 0, 0, 0, _, _] if !ip.is_unspecified() && !ip.is_loopback() => Some(last),
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(last),
+        [0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+        _ => None,
+    }
+}
+
+/// Loopback, private, link-local, shared and other non-public addresses.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_internal_v4(v4),
+        IpAddr::V6(v6) => is_internal_v6(v6),
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space (carrier-grade NAT), 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Documentation, 192.0.2.0/24, 198.51.100.0/24 and 203.0.113.0/24
+        || matches!((a, b, c), (192, 0, 2) | (198, 51, 100) | (203, 0, 113))
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved, 240.0.0.0/4
+        || a >= 240
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // Local-use NAT64, 64:ff9b:1::/48
+        || ip.segments()[..3] == [0x64, 0xff9b, 1]
+        // Documentation, 2001:db8::/32
+        || ip.segments()[..2] == [0x2001, 0xdb8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn blocks_internal_addresses_by_default() {
+        let policy = EgressPolicy::default();
+
+        for target in [
+            "http://127.0.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.1.2.3/",
+            "http://192.168.0.1:8080/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd00::1]/",
+            // IPv4-compatible, NAT64 and 6to4 forms of internal addresses
+            "http://[::127.0.0.1]/",
+            "http://[::a9fe:a9fe]/",
+            "http://[64:ff9b::10.0.0.1]/",
+            "http://[64:ff9b:1::1]/",
+            "http://[2002:a9fe:a9fe::]/",
+            "http://[2002:7f00:1::1]/",
+            "http://[2001:db8::1]/",
+            "http://192.0.0.8/",
+            "http://192.0.2.1/",
+            "http://198.51.100.1/",
+            "http://203.0.113.1/",
+            "http://198.18.0.1/",
+            "http://198.19.255.255/",
+            "http://240.0.0.1/",
+        ] {
+            assert!(policy.check_url(&url(target)).is_err(), "{target} should be blocked");
+        }
+
+        assert!(policy.check_url(&url("https://93.184.215.14/")).is_ok());
+        assert!(policy.check_url(&url("https://[64:ff9b::5db8:d70e]/")).is_ok());
+        assert!(policy.check_url(&url("https://[2002:5db8:d70e::1]/")).is_ok());
+        assert!(policy.check_url(&url("https://198.20.0.1/")).is_ok());
+        assert!(policy.check_url(&url("https://example.com/")).is_ok());
+    }
+
+    #[test]
+    fn allowed_cidrs_and_private_networks_are_reachable() {
+        let policy = EgressPolicy {
+            allow_cidrs: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(policy.check_url(&url("http://10.1.2.3/")).is_ok());
+        assert!(policy.check_url(&url("http://192.168.0.1/")).is_err());
+
+        let policy = EgressPolicy {
+            allow_private_networks: true,
+            deny_cidrs: vec!["169.254.0.0/16".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(policy.check_url(&url("http://127.0.0.1/")).is_ok());
+        assert!(policy.check_url(&url("http://169.254.169.254/")).is_err());
+    }
+
+    #[test]
+    fn checks_hosts_and_ports() {
+        let policy = EgressPolicy {
+            allow_hosts: vec!["*.example.com".to_string(), "api.test".to_string()],
+            deny_hosts: vec!["admin.example.com".to_string()],
+            allow_ports: vec![443],
+            ..Default::default()
+        };
+
+        assert!(policy.check_url(&url("https://www.example.com/")).is_ok());
+        assert!(policy.check_url(&url("https://API.test/")).is_ok());
+        assert!(policy.check_url(&url("https://example.com/")).is_err());
+        assert!(policy.check_url(&url("https://evilexample.com/")).is_err());
+        assert!(policy.check_url(&url("https://admin.example.com/")).is_err());
+        assert!(policy.check_url(&url("http://www.example.com/")).is_err());
+        assert!(policy.check_url(&url("https://www.example.com:8443/")).is_err());
+    }
+
+    #[test]
+    fn denied_cause_finds_the_policy_error() {
+        let denied = EgressDenied("host x is denied".to_string());
+        assert!(denied_cause(&denied).is_some());
+
+        let io = std::io::Error::other("unrelated");
+        assert!(denied_cause(&io).is_none());
+    }
+}
@@ -1,4 +1,6 @@
+use crate::http_log::HttpLogEntry;
 use crate::types::{AppError, ErrorCode};
+use crate::wrappers::http_log::http_log_array;
 use magnus::prelude::*;
 use magnus::{Error, ExceptionClass, RObject, Ruby};
 
@@ -23,26 +25,42 @@ impl From<ErrorCode> for ExceptionClass {
             ErrorCode::RetryWithReference(_) => get_class("AppBridge::RetryWithReferenceError"),
             ErrorCode::CompleteWorkflow => get_class("AppBridge::CompleteWorkflowException"),
             ErrorCode::CompleteParent => get_class("AppBridge::CompleteParentException"),
+            ErrorCode::FuelExhausted => get_class("AppBridge::FuelExhaustedError"),
+            ErrorCode::ResourceLimit => get_class("AppBridge::ResourceLimitError"),
         }
     }
 }
 
 impl From<AppError> for Error {
     fn from(value: AppError) -> Self {
-        if let ErrorCode::RetryWithReference(retry) = value.code.clone() {
-            let class: ExceptionClass = value.code.into();
-            let message = value.message;
-            if let Ok(exception) = class.new_instance((message.as_str(),)) {
-                if let Ok(exception_value) = RObject::try_convert(exception.as_value()) {
-                    let _ = exception_value.ivar_set("@reference", retry.reference);
-                    let _ = exception_value.ivar_set("@status", retry.status);
-                }
-                return Error::from(exception);
-            }
+        exception_for(value, |_| Ok(()))
+    }
+}
 
-            return Error::new(class, message);
-        }
+/// Converts the error of an invocation, attaching the HTTP requests it made
+/// for `AppBridge::Error#http_log`.
+pub fn invocation_error(value: AppError, http_log: &[HttpLogEntry]) -> Error {
+    exception_for(value, |exception| {
+        let ruby = Ruby::get().unwrap();
+        exception.ivar_set("@http_log", http_log_array(&ruby, http_log)?)
+    })
+}
 
-        Error::new(value.code.into(), value.message)
+/// Instantiates the exception for `value`, setting the ivars of
+/// `RetryWithReferenceError` and any others `set_ivars` sets.
+fn exception_for(value: AppError, set_ivars: impl FnOnce(RObject) -> Result<(), Error>) -> Error {
+    let class: ExceptionClass = value.code.clone().into();
+    let Ok(exception) = class.new_instance((value.message.as_str(),)) else {
+        return Error::new(class, value.message);
+    };
+
+    if let Ok(exception_value) = RObject::try_convert(exception.as_value()) {
+        if let ErrorCode::RetryWithReference(retry) = value.code {
+            let _ = exception_value.ivar_set("@reference", retry.reference);
+            let _ = exception_value.ivar_set("@status", retry.status);
+        }
+        let _ = set_ivars(exception_value);
     }
+
+    Error::from(exception)
 }
@@ -1,5 +1,6 @@
 use crate::app_state::AppState;
-use crate::component::{v4, v4_1};
+use crate::component::{v4, v4_1, v4_2};
+use crate::transport::{HttpRequest, RequestError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 
 /// Detects the type of input string
@@ -72,7 +73,7 @@ fn detect_content_type(bytes: &[u8]) -> String {
 
 /// Internal error type for file operations - converted to version-specific errors by macros
 #[derive(Debug)]
-#[allow(dead_code)] // Timeout and Other are needed for WIT compatibility but not currently used
+#[allow(dead_code)] // Other is needed for WIT compatibility but not currently used
 enum NormalizeError {
     FetchFailed(String),
     InvalidInput(String),
@@ -140,45 +141,49 @@ fn urlencoding_decode(input: &str) -> Result<Vec<u8>, String> {
 
 /// Fetches content from a URL with optional headers
 fn fetch_url(
-    client: &reqwest::blocking::Client,
+    state: &AppState,
     url: &str,
     headers: Option<&Vec<(String, String)>>,
 ) -> Result<(Vec<u8>, Option<String>, Option<String>), NormalizeError> {
-    let mut request = client.get(url);
-
-    // Add custom headers if provided
-    if let Some(hdrs) = headers {
-        for (key, value) in hdrs {
-            request = request.header(key, value);
-        }
+    let mut headers = headers.cloned().unwrap_or_default();
+    if !headers.iter().any(|(key, _)| key.eq_ignore_ascii_case("user-agent")) {
+        headers.push(("User-Agent".to_string(), state.http.user_agent()));
     }
 
-    let response = request
-        .send()
-        .map_err(|e| NormalizeError::FetchFailed(format!("Request failed: {}", e)))?;
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        headers,
+        body: Vec::new(),
+        timeout: state.http.timeout_for(None, state.deadline),
+        max_response_size: state.http.max_response_size,
+    };
+
+    let response = state.send_http(&request).map_err(|e| match e {
+        RequestError::Timeout(msg) => NormalizeError::Timeout(msg),
+        e => NormalizeError::FetchFailed(e.into_message()),
+    })?;
 
-    if !response.status().is_success() {
+    if !(200..300).contains(&response.status) {
+        let reason = reqwest::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|status| status.canonical_reason());
         return Err(NormalizeError::FetchFailed(format!(
             "HTTP {} {}",
-            response.status().as_u16(),
-            response.status().canonical_reason().unwrap_or("Unknown")
+            response.status,
+            reason.unwrap_or("Unknown")
         )));
     }
 
     let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(';').next().unwrap_or(s).trim().to_string());
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.split(';').next().unwrap_or(value).trim().to_string());
 
     let filename = filename_from_url(url);
 
-    let bytes = response
-        .bytes()
-        .map_err(|e| NormalizeError::FetchFailed(format!("Failed to read response body: {}", e)))?
-        .to_vec();
-
-    Ok((bytes, content_type, filename))
+    Ok((response.body, content_type, filename))
 }
 
 // ============================================================================
@@ -186,16 +191,13 @@ fn fetch_url(
 // ============================================================================
 
 fn normalize_file(
-    client: &std::sync::Arc<std::sync::Mutex<reqwest::blocking::Client>>,
+    state: &AppState,
     source: &str,
     headers: Option<&Vec<(String, String)>>,
     filename: Option<String>,
 ) -> Result<(String, String, String), NormalizeError> {
     let (bytes, content_type, url_filename) = match detect_input_type(source) {
-        InputType::Url => {
-            let client = client.lock().unwrap();
-            fetch_url(&client, source, headers)?
-        }
+        InputType::Url => fetch_url(state, source, headers)?,
         InputType::DataUri => {
             let (ct, bytes) = parse_data_uri(source)?;
             (bytes, Some(ct), None)
@@ -234,7 +236,7 @@ macro_rules! impl_file_host {
                 headers: Option<Vec<(String, String)>>,
                 filename: Option<String>,
             ) -> Result<$v::standout::app::file::FileData, $v::standout::app::file::FileError> {
-                match normalize_file(&self.client, &source, headers.as_ref(), filename) {
+                match normalize_file(self, &source, headers.as_ref(), filename) {
                     Ok((base64, content_type, filename)) => Ok($v::standout::app::file::FileData {
                         base64,
                         content_type,
@@ -257,6 +259,7 @@ macro_rules! impl_file_host {
 // When adding v5, add: impl_file_host!(v5);
 impl_file_host!(v4);
 impl_file_host!(v4_1);
+impl_file_host!(v4_2);
 
 #[cfg(test)]
 mod tests {
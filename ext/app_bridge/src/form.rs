@@ -0,0 +1,160 @@
+//! Form bodies the host encodes for the request builder.
+//!
+//! `request-builder.form` sends `application/x-www-form-urlencoded` fields
+//! and `request-builder.multipart` sends `multipart/form-data` parts, where a
+//! file part takes the `file-data` that `file.normalize` returns. The body is
+//! encoded when the request is sent, and its `Content-Type` replaces any the
+//! component set.
+//!
+//! The multipart boundary is the first of `app-bridge-boundary-0`,
+//! `-1`, ... that occurs in no part, so the same parts always encode to the
+//! same body and replay from a cassette.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+const BOUNDARY_PREFIX: &str = "app-bridge-boundary-";
+
+/// A part of a multipart body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormPart {
+    Text {
+        name: String,
+        value: String,
+    },
+    File {
+        name: String,
+        filename: String,
+        content_type: String,
+        /// The content, base64-encoded as in `file-data`.
+        base64: String,
+    },
+}
+
+/// Encodes `fields` as `application/x-www-form-urlencoded`.
+pub fn encode_form(fields: &[(String, String)]) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(fields)
+        .finish()
+}
+
+/// Encodes `parts` as `multipart/form-data`. Returns the body and its
+/// `Content-Type`, or an error naming a file part that isn't valid base64.
+pub fn encode_multipart(parts: &[FormPart]) -> Result<(Vec<u8>, String), String> {
+    let parts = parts
+        .iter()
+        .map(|part| match part {
+            FormPart::Text { name, value } => Ok((disposition(name, None), value.as_bytes().to_vec())),
+            FormPart::File {
+                name,
+                filename,
+                content_type,
+                base64,
+            } => {
+                let content = BASE64
+                    .decode(base64)
+                    .map_err(|e| format!("Invalid base64 in multipart file part {name}: {e}"))?;
+                let headers = format!(
+                    "{}\r\nContent-Type: {}",
+                    disposition(name, Some(filename)),
+                    content_type.replace(['\r', '\n'], "")
+                );
+                Ok((headers, content))
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let boundary = (0..)
+        .map(|n| format!("{BOUNDARY_PREFIX}{n}"))
+        .find(|boundary| {
+            !parts
+                .iter()
+                .any(|(headers, content)| contains(headers.as_bytes(), boundary) || contains(content, boundary))
+        })
+        .expect("some boundary occurs in no part");
+
+    let mut body = Vec::new();
+    for (headers, content) in &parts {
+        body.extend_from_slice(format!("--{boundary}\r\n{headers}\r\n\r\n").as_bytes());
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    Ok((body, format!("multipart/form-data; boundary={boundary}")))
+}
+
+/// The `Content-Disposition` header of a part, escaping the name and
+/// filename the way browsers do.
+fn disposition(name: &str, filename: Option<&str>) -> String {
+    let escape = |value: &str| value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+    match filename {
+        Some(filename) => format!(
+            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"",
+            escape(name),
+            escape(filename)
+        ),
+        None => format!("Content-Disposition: form-data; name=\"{}\"", escape(name)),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_form_fields() {
+        let fields = [
+            ("name".to_string(), "Jane Doe".to_string()),
+            ("note".to_string(), "a&b=c/é".to_string()),
+        ];
+        assert_eq!(encode_form(&fields), "name=Jane+Doe&note=a%26b%3Dc%2F%C3%A9");
+    }
+
+    #[test]
+    fn encodes_multipart_parts() {
+        let parts = [
+            FormPart::Text {
+                name: "title".to_string(),
+                value: "Invoice \"42\"".to_string(),
+            },
+            FormPart::File {
+                name: "file".to_string(),
+                filename: "invoice.pdf".to_string(),
+                content_type: "application/pdf".to_string(),
+                base64: BASE64.encode("%PDF-1.3 app-bridge-boundary-0"),
+            },
+        ];
+
+        let (body, content_type) = encode_multipart(&parts).unwrap();
+
+        assert_eq!(content_type, "multipart/form-data; boundary=app-bridge-boundary-1");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--app-bridge-boundary-1\r\n\
+             Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+             Invoice \"42\"\r\n\
+             --app-bridge-boundary-1\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"invoice.pdf\"\r\n\
+             Content-Type: application/pdf\r\n\r\n\
+             %PDF-1.3 app-bridge-boundary-0\r\n\
+             --app-bridge-boundary-1--\r\n"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_base64() {
+        let parts = [FormPart::File {
+            name: "file".to_string(),
+            filename: "a.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            base64: "not base64!".to_string(),
+        }];
+
+        let error = encode_multipart(&parts).unwrap_err();
+        assert!(error.starts_with("Invalid base64 in multipart file part file"), "got {error}");
+    }
+}
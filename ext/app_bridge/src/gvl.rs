@@ -0,0 +1,98 @@
+//! Releasing the Ruby GVL while components run.
+//!
+//! Running wasm and the blocking HTTP requests a component makes never touch
+//! Ruby objects, so the GVL is released for the duration of a call and other
+//! Ruby threads keep running in the meantime. Host functions that do need
+//! Ruby, such as a Ruby HTTP transport, take it back with `with_gvl`.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+thread_local! {
+    /// Whether this thread released the GVL in `without_gvl`.
+    static GVL_RELEASED: Cell<bool> = const { Cell::new(false) };
+}
+
+struct Call<F, R> {
+    func: Option<F>,
+    result: Option<Result<R, Box<dyn Any + Send>>>,
+}
+
+unsafe extern "C" fn call_func<F, R, const RELEASED: bool>(data: *mut c_void) -> *mut c_void
+where
+    F: FnOnce() -> R,
+{
+    let call = &mut *(data as *mut Call<F, R>);
+    if let Some(func) = call.func.take() {
+        let outer = GVL_RELEASED.replace(RELEASED);
+        // Unwinding out of an `extern "C"` function aborts the process, so the
+        // panic is carried back and resumed on the other side.
+        call.result = Some(panic::catch_unwind(AssertUnwindSafe(func)));
+        GVL_RELEASED.set(outer);
+    }
+    ptr::null_mut()
+}
+
+/// Runs `func` without holding the GVL.
+///
+/// `func` must not call into Ruby or touch Ruby objects, so convert any Ruby
+/// values into plain Rust types before calling this.
+pub fn without_gvl<F, R>(func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let mut call = Call {
+        func: Some(func),
+        result: None,
+    };
+
+    unsafe {
+        rb_sys::rb_thread_call_without_gvl(
+            Some(call_func::<F, R, true>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+            None,
+            ptr::null_mut(),
+        );
+    }
+
+    match call.result {
+        Some(Ok(result)) => result,
+        Some(Err(payload)) => panic::resume_unwind(payload),
+        // Ruby may return without calling `func` when the thread has pending
+        // interrupts. Run it while holding the GVL instead.
+        None => (call.func.take().expect("func is only taken when it runs"))(),
+    }
+}
+
+/// Runs `func` holding the GVL, from inside a `without_gvl` call or from a
+/// Ruby thread that holds it already. Returns `None` on threads Ruby doesn't
+/// know about, which can never take the GVL.
+pub fn with_gvl<F, R>(func: F) -> Option<R>
+where
+    F: FnOnce() -> R,
+{
+    if !GVL_RELEASED.get() {
+        return unsafe { rb_sys::ruby_native_thread_p() != 0 }.then(func);
+    }
+
+    let mut call = Call {
+        func: Some(func),
+        result: None,
+    };
+
+    unsafe {
+        rb_sys::rb_thread_call_with_gvl(
+            Some(call_func::<F, R, false>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+        );
+    }
+
+    match call.result {
+        Some(Ok(result)) => Some(result),
+        Some(Err(payload)) => panic::resume_unwind(payload),
+        None => unreachable!("rb_thread_call_with_gvl always calls func"),
+    }
+}
@@ -0,0 +1,285 @@
+//! Caching responses so that polling triggers can ask whether they changed.
+//!
+//! With a cache, GET requests a component sends with `request-builder` during
+//! `fetch_events` and `execute_action` are cached per connection and URL when
+//! the response carries an `ETag` or `Last-Modified`. Sending the request
+//! again adds `If-None-Match` and `If-Modified-Since`, and a
+//! `304 Not Modified` answer is replaced by the cached response, so the
+//! server doesn't send an unchanged collection again. The headers the 304
+//! carries, like a new `ETag` or `Cache-Control`, replace the cached ones and
+//! the entry is stored again, as RFC 9111 section 4.3.4 asks.
+//!
+//! The key hashes the connection id, the URL and the `KEYED_HEADERS` the
+//! request carries, so neither shows up in the store and requests made with
+//! other credentials or for another representation don't share an entry. A
+//! response whose `Vary` names any other header can't be told apart by the
+//! key and isn't cached, and neither are responses marked
+//! `Cache-Control: no-store`. Requests that set their own conditional headers
+//! bypass the cache. Entries are JSON strings, and a store that fails is
+//! treated as empty.
+
+use crate::app_state::AppState;
+use crate::cassette::{RecordedBody, RecordedResponse};
+use crate::transport::{HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Prefix of the keys entries are stored under.
+const KEY_PREFIX: &str = "app_bridge/http/";
+
+/// Request headers that make a request conditional.
+const CONDITIONAL_HEADERS: [&str; 2] = ["if-none-match", "if-modified-since"];
+
+/// Request headers that are part of the key: who is asking, and which
+/// representation they ask for.
+const KEYED_HEADERS: [&str; 5] = ["authorization", "cookie", "accept", "accept-language", "accept-encoding"];
+
+/// Headers of a 304 that describe its own message rather than the cached
+/// representation, so they don't replace the cached ones.
+const MESSAGE_HEADERS: [&str; 3] = ["content-length", "content-encoding", "transfer-encoding"];
+
+/// Where cached responses are kept.
+pub trait HttpCache: fmt::Debug + Send + Sync {
+    fn read(&self, key: &str) -> Result<Option<String>, String>;
+    fn write(&self, key: &str, value: &str) -> Result<(), String>;
+}
+
+/// A request the cache was consulted for, and what it held.
+#[derive(Debug)]
+pub struct CacheLookup {
+    key: String,
+    cached: Option<HttpResponse>,
+}
+
+impl AppState {
+    /// Looks up `request` in the app's cache and makes it conditional on the
+    /// cached response. `None` when the request isn't cached: without a
+    /// cache or a running invocation, or when it isn't a plain GET.
+    pub fn cache_lookup(&self, request: &mut HttpRequest) -> Option<CacheLookup> {
+        let cache = self.http.cache.as_deref()?;
+        let connection_id = self.connection_id.as_deref()?;
+        let conditional = request
+            .headers
+            .iter()
+            .any(|(name, _)| CONDITIONAL_HEADERS.contains(&name.to_ascii_lowercase().as_str()));
+        if request.method != "GET" || conditional {
+            return None;
+        }
+
+        let key = cache_key(connection_id, request);
+        let cached = cache
+            .read(&key)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str::<RecordedResponse>(&json).ok())
+            .and_then(|recorded| {
+                Some(HttpResponse {
+                    status: recorded.status,
+                    body: recorded.body.to_bytes().ok()?,
+                    headers: recorded.headers,
+                })
+            });
+
+        if let Some(cached) = &cached {
+            if let Some(etag) = header(cached, "etag") {
+                request.headers.push(("If-None-Match".to_string(), etag.to_string()));
+            }
+            if let Some(last_modified) = header(cached, "last-modified") {
+                request.headers.push(("If-Modified-Since".to_string(), last_modified.to_string()));
+            }
+        }
+
+        Some(CacheLookup { key, cached })
+    }
+
+    /// Completes a cached request: a 304 is answered with the cached
+    /// response freshened by its headers, and a new 200 with a validator is
+    /// stored, as is the freshened response.
+    pub fn cache_response(&self, lookup: CacheLookup, response: HttpResponse) -> HttpResponse {
+        let Some(cache) = self.http.cache.as_deref() else {
+            return response;
+        };
+
+        let response = match lookup.cached {
+            Some(cached) if response.status == 304 => freshen(cached, &response),
+            _ => response,
+        };
+        if cacheable(&response) {
+            let recorded = RecordedResponse {
+                status: response.status,
+                headers: response.headers.clone(),
+                body: RecordedBody::new(&response.body),
+            };
+            if let Ok(json) = serde_json::to_string(&recorded) {
+                let _ = cache.write(&lookup.key, &json);
+            }
+        }
+        response
+    }
+}
+
+/// `cached` with the headers of `not_modified` in place of its own.
+fn freshen(mut cached: HttpResponse, not_modified: &HttpResponse) -> HttpResponse {
+    let updated = |name: &str| {
+        not_modified.headers.iter().any(|(key, _)| key.eq_ignore_ascii_case(name))
+            && !MESSAGE_HEADERS.contains(&name.to_ascii_lowercase().as_str())
+    };
+
+    cached.headers.retain(|(name, _)| !updated(name));
+    cached.headers.extend(
+        not_modified
+            .headers
+            .iter()
+            .filter(|(name, _)| updated(name))
+            .cloned(),
+    );
+    cached
+}
+
+/// The key the response to `request` is cached under.
+fn cache_key(connection_id: &str, request: &HttpRequest) -> String {
+    let mut hasher = Sha256::new()
+        .chain_update(connection_id)
+        .chain_update([0])
+        .chain_update(&request.url);
+    for keyed in KEYED_HEADERS {
+        for (_, value) in request.headers.iter().filter(|(name, _)| name.eq_ignore_ascii_case(keyed)) {
+            hasher.update([0]);
+            hasher.update(keyed);
+            hasher.update([0]);
+            hasher.update(value);
+        }
+    }
+
+    let hex: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{KEY_PREFIX}{hex}")
+}
+
+fn cacheable(response: &HttpResponse) -> bool {
+    let no_store = header(response, "cache-control")
+        .is_some_and(|value| value.to_ascii_lowercase().contains("no-store"));
+    let validated = header(response, "etag").is_some() || header(response, "last-modified").is_some();
+    // `Vary: *` is never keyed, so it isn't cached either.
+    let keyed = response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+        .flat_map(|(_, value)| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .all(|name| name.is_empty() || KEYED_HEADERS.contains(&name.as_str()));
+
+    response.status == 200 && validated && keyed && !no_store
+}
+
+fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+    response
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn request(url: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: owned(headers),
+            body: Vec::new(),
+            timeout: None,
+            max_response_size: None,
+        }
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: owned(headers),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keys_hash_the_connection_and_url() {
+        let url = "https://api.example.com/items?token=s3cret";
+        let key = cache_key("connection-1", &request(url, &[]));
+
+        assert!(key.starts_with(KEY_PREFIX) && !key.contains("s3cret"), "{key}");
+        assert_ne!(key, cache_key("connection-2", &request(url, &[])));
+        assert_eq!(key, cache_key("connection-1", &request(url, &[])));
+    }
+
+    #[test]
+    fn keys_depend_on_credentials_and_negotiated_headers() {
+        let url = "https://api.example.com/items";
+        let key = |headers: &[(&str, &str)]| cache_key("connection-1", &request(url, headers));
+
+        let alice = key(&[("Authorization", "Bearer alice")]);
+        assert_ne!(alice, key(&[("Authorization", "Bearer bob")]));
+        assert_ne!(alice, key(&[]));
+        assert_eq!(alice, key(&[("authorization", "Bearer alice"), ("X-Request-Id", "1")]));
+
+        assert_ne!(key(&[("Accept", "application/json")]), key(&[("Accept", "text/csv")]));
+        assert_ne!(key(&[("Accept-Language", "en")]), key(&[("Accept-Language", "de")]));
+    }
+
+    #[test]
+    fn freshens_cached_responses_with_the_headers_of_a_304() {
+        let cached = HttpResponse {
+            body: b"[1,2,3]".to_vec(),
+            ..response(
+                200,
+                &[
+                    ("ETag", "\"v1\""),
+                    ("Content-Type", "application/json"),
+                    ("Content-Length", "7"),
+                    ("Cache-Control", "max-age=60"),
+                ],
+            )
+        };
+        let not_modified = response(
+            304,
+            &[("etag", "\"v2\""), ("Cache-Control", "max-age=120"), ("Content-Length", "0")],
+        );
+
+        let fresh = freshen(cached, &not_modified);
+
+        assert_eq!(fresh.status, 200);
+        assert_eq!(fresh.body, b"[1,2,3]");
+        assert_eq!(
+            fresh.headers,
+            owned(&[
+                ("Content-Type", "application/json"),
+                ("Content-Length", "7"),
+                ("etag", "\"v2\""),
+                ("Cache-Control", "max-age=120"),
+            ])
+        );
+    }
+
+    #[test]
+    fn caches_responses_that_vary_only_on_keyed_headers() {
+        let etag = ("ETag", "\"v1\"");
+
+        assert!(cacheable(&response(200, &[etag, ("Vary", "Accept, Accept-Encoding")])));
+        assert!(cacheable(&response(200, &[etag, ("vary", "authorization"), ("Vary", "Accept-Language")])));
+        assert!(!cacheable(&response(200, &[etag, ("Vary", "Accept, X-Tenant")])));
+        assert!(!cacheable(&response(200, &[etag, ("Vary", "*")])));
+    }
+
+    #[test]
+    fn caches_validated_ok_responses_unless_told_not_to() {
+        assert!(cacheable(&response(200, &[("ETag", "\"v1\"")])));
+        assert!(cacheable(&response(200, &[("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")])));
+        assert!(!cacheable(&response(200, &[])));
+        assert!(!cacheable(&response(201, &[("ETag", "\"v1\"")])));
+        assert!(!cacheable(&response(200, &[("ETag", "\"v1\""), ("Cache-Control", "private, no-store")])));
+    }
+}
@@ -0,0 +1,26 @@
+//! Log of the HTTP requests a component makes during an invocation.
+//!
+//! Every request sent through `AppState::send_http`, from the http interface
+//! or from `file.normalize`, is logged with its outcome. The log is handed to
+//! Ruby with the invocation's response, or with the error it raised, so a
+//! failing connector can be traced. URLs and errors are logged as the app's
+//! `Redactor` redacts them.
+
+use std::time::Duration;
+
+/// One request and its outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpLogEntry {
+    pub method: String,
+    /// The URL, redacted with `Redactor::url`.
+    pub url: String,
+    /// The response status, if a response was received.
+    pub status: Option<u16>,
+    pub duration: Duration,
+    /// Size of the request body in bytes.
+    pub request_size: usize,
+    /// Size of the response body in bytes, if a response was received.
+    pub response_size: Option<usize>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
@@ -0,0 +1,143 @@
+//! Host-wide settings for the HTTP requests components make.
+
+use crate::cassette::Cassette;
+use crate::egress::EgressPolicy;
+use crate::http_cache::HttpCache;
+use crate::redact::Redactor;
+use crate::retry::RetryPolicy;
+use crate::transport::Transport;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Requests of a batch sent at the same time unless configured otherwise.
+pub const DEFAULT_MAX_PARALLELISM: usize = 8;
+
+/// HTTP settings shared by every store of an app.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Timeout for requests that don't set their own.
+    pub timeout: Option<Duration>,
+    /// Retry policy for requests that don't set their own.
+    pub retry: Option<RetryPolicy>,
+    /// Largest response body accepted, in bytes.
+    pub max_response_size: Option<usize>,
+    /// Requests of a batch sent at the same time, at most.
+    pub max_parallelism: Option<usize>,
+    /// Which servers components may connect to.
+    pub egress: Arc<EgressPolicy>,
+    /// What is redacted from request errors and the HTTP log.
+    pub redactor: Arc<Redactor>,
+    /// Cassette that records or replays the requests.
+    pub cassette: Option<Arc<Cassette>>,
+    /// Caches GET responses for conditional requests.
+    pub cache: Option<Arc<dyn HttpCache>>,
+    /// Sends the requests instead of the built-in client.
+    pub transport: Option<Arc<dyn Transport>>,
+    /// How the built-in client connects.
+    pub client: ClientOptions,
+}
+
+/// Settings for the built-in HTTP client.
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Proxy every request is sent through, e.g. `http://proxy.internal:3128`.
+    pub proxy: Option<String>,
+    /// PEM certificates trusted in addition to the system's.
+    pub root_certificates: Vec<Vec<u8>>,
+    /// Certificate presented to servers that ask for one.
+    pub identity: Option<ClientIdentity>,
+    /// Redirects followed before a request fails. With 0 the redirect
+    /// response itself is returned.
+    pub max_redirects: Option<usize>,
+    /// User-Agent of requests that don't set their own.
+    pub user_agent: Option<String>,
+}
+
+/// A client certificate and its private key.
+#[derive(Clone)]
+pub struct ClientIdentity {
+    /// PEM certificate chain.
+    pub certificate: Vec<u8>,
+    /// PEM PKCS#8 private key.
+    pub key: Vec<u8>,
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClientIdentity")
+    }
+}
+
+impl HttpOptions {
+    /// The User-Agent of requests that don't set their own.
+    pub fn user_agent(&self) -> String {
+        self.client
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| format!("Standout-AppBridge/{}", env!("CARGO_PKG_VERSION")))
+    }
+
+    /// How many requests of a batch to send at the same time: the number the
+    /// component `requested`, capped at the host maximum and at least 1.
+    pub fn parallelism_for(&self, requested: u32) -> usize {
+        let max = self.max_parallelism.unwrap_or(DEFAULT_MAX_PARALLELISM);
+        usize::try_from(requested).unwrap_or(usize::MAX).clamp(1, max.max(1))
+    }
+
+    /// Timeout for a single request: its own timeout or the host default,
+    /// capped at the time left until the invocation `deadline`.
+    pub fn timeout_for(&self, requested: Option<Duration>, deadline: Option<Instant>) -> Option<Duration> {
+        let timeout = requested.or(self.timeout);
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+        match (timeout, remaining) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_timeout_overrides_default() {
+        let options = HttpOptions {
+            timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+
+        assert_eq!(options.timeout_for(None, None), Some(Duration::from_secs(10)));
+        assert_eq!(
+            options.timeout_for(Some(Duration::from_secs(2)), None),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn timeout_is_capped_at_the_deadline() {
+        let options = HttpOptions::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let timeout = options.timeout_for(Some(Duration::from_secs(60)), Some(deadline)).unwrap();
+        assert!(timeout <= Duration::from_secs(1));
+
+        let passed = Instant::now() - Duration::from_secs(1);
+        assert_eq!(options.timeout_for(None, Some(passed)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn parallelism_is_capped_at_the_host_maximum() {
+        let options = HttpOptions {
+            max_parallelism: Some(4),
+            ..Default::default()
+        };
+
+        assert_eq!(options.parallelism_for(2), 2);
+        assert_eq!(options.parallelism_for(100), 4);
+        assert_eq!(options.parallelism_for(0), 1);
+        assert_eq!(HttpOptions::default().parallelism_for(100), DEFAULT_MAX_PARALLELISM);
+    }
+}
@@ -1,9 +1,26 @@
 use magnus::{function, method, prelude::*, Error, RObject, Ruby, Value};
 mod app_state;
+mod artifact_cache;
+mod batch;
+mod cassette;
 mod component;
+mod egress;
 mod error_mapping;
 mod file_ops;
+mod form;
+mod gvl;
+mod http_cache;
+mod http_log;
+mod http_options;
+mod limits;
+mod pool;
+mod redact;
+mod registry;
 mod request_builder;
+mod retry;
+mod ruby_http_cache;
+mod ruby_transport;
+mod transport;
 mod types;
 
 mod wrappers;
@@ -40,6 +57,8 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     module.define_error("InternalError", error)?;
     module.define_error("MalformedResponseError", error)?;
     module.define_error("OtherError", error)?;
+    module.define_error("FuelExhaustedError", error)?;
+    module.define_error("ResourceLimitError", error)?;
     let retry_error = module.define_error("RetryWithReferenceError", error)?;
     retry_error.define_method("reference", method!(retry_reference, 0))?;
     retry_error.define_method("status", method!(retry_status, 0))?;
@@ -65,6 +84,8 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     trigger_response_class.define_singleton_method("new", function!(RTriggerResponse::new, 2))?;
     trigger_response_class.define_method("store", method!(RTriggerResponse::store, 0))?;
     trigger_response_class.define_method("events", method!(RTriggerResponse::events, 0))?;
+    trigger_response_class.define_method("fuel_consumed", method!(RTriggerResponse::fuel_consumed, 0))?;
+    trigger_response_class.define_method("http_log", method!(RTriggerResponse::http_log, 0))?;
 
     let trigger_context_class = module.define_class("TriggerContext", ruby.class_object())?;
     trigger_context_class.define_singleton_method("new", function!(RTriggerContext::new, 4))?;
@@ -85,10 +106,13 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     action_response_class.define_singleton_method("new", function!(RActionResponse::new, 1))?;
     action_response_class.define_method("serialized_output", method!(RActionResponse::serialized_output, 0))?;
     action_response_class.define_method("with_output", method!(RActionResponse::with_output, 1))?;
+    action_response_class.define_method("fuel_consumed", method!(RActionResponse::fuel_consumed, 0))?;
+    action_response_class.define_method("http_log", method!(RActionResponse::http_log, 0))?;
 
     // Define the App class
     let app_class = module.define_class("App", ruby.class_object())?;
     app_class.define_alloc_func::<MutRApp>();
+    wrappers::app_options::define_constants(ruby, app_class)?;
     app_class.define_method("wit_version", method!(MutRApp::wit_version, 0))?;
     app_class.define_method("trigger_ids", method!(MutRApp::trigger_ids, 0))?;
     app_class.define_method("action_ids", method!(MutRApp::action_ids, 0))?;
@@ -96,9 +120,10 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     app_class.define_method("action_output_schema", method!(MutRApp::action_output_schema, 1))?;
     app_class.define_method("trigger_input_schema", method!(MutRApp::trigger_input_schema, 1))?;
     app_class.define_method("trigger_output_schema", method!(MutRApp::trigger_output_schema, 1))?;
-    app_class.define_private_method("_rust_initialize", method!(MutRApp::initialize, 2))?;
-    app_class.define_private_method("_rust_fetch_events", method!(MutRApp::rb_fetch_events, 1))?;
-    app_class.define_private_method("_rust_execute_action", method!(MutRApp::rb_execute_action, 1))?;
+    app_class.define_singleton_method("_rust_precompile", function!(MutRApp::precompile, 3))?;
+    app_class.define_private_method("_rust_initialize", method!(MutRApp::initialize, 3))?;
+    app_class.define_private_method("_rust_fetch_events", method!(MutRApp::rb_fetch_events, 2))?;
+    app_class.define_private_method("_rust_execute_action", method!(MutRApp::rb_execute_action, 2))?;
 
     Ok(())
 }
@@ -0,0 +1,147 @@
+//! Resource limits applied to component stores.
+//!
+//! Exceeding a limit while a component runs fails the invocation with
+//! `ResourceLimitExceeded`, which surfaces as `AppBridge::ResourceLimitError`.
+//! wasmtime counts instances and tables itself and fails instantiation with a
+//! plain error when there are too many, which `ResourceLimitExceeded::find`
+//! recognizes.
+
+use std::fmt;
+use wasmtime::{
+    ResourceLimiter, Result, DEFAULT_INSTANCE_LIMIT, DEFAULT_MEMORY_LIMIT, DEFAULT_TABLE_LIMIT,
+};
+
+/// Limits for a single store. `None` leaves a resource unlimited (or at the
+/// wasmtime default for counts).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResourceLimits {
+    /// Maximum size in bytes of each linear memory.
+    pub max_memory: Option<usize>,
+    /// Maximum number of elements in each table.
+    pub max_table_elements: Option<usize>,
+    /// Maximum number of instances in the store.
+    pub max_instances: Option<usize>,
+    /// Maximum number of tables in the store.
+    pub max_tables: Option<usize>,
+}
+
+/// Error returned from the limiter when a component grows past a limit.
+#[derive(Debug, Clone)]
+pub enum ResourceLimitExceeded {
+    Memory { desired: usize, limit: usize },
+    TableElements { desired: usize, limit: usize },
+    Instances { desired: usize, limit: usize },
+    Tables { desired: usize, limit: usize },
+}
+
+impl ResourceLimitExceeded {
+    /// The limit `err` reports exceeding, given the `limits` of the store it
+    /// happened in.
+    pub fn find(err: &wasmtime::Error, limits: &ResourceLimits) -> Option<Self> {
+        if let Some(exceeded) = err.downcast_ref::<Self>() {
+            return Some(exceeded.clone());
+        }
+
+        // wasmtime's "resource limit exceeded: instance count too high at 11".
+        err.chain().find_map(|cause| {
+            let message = cause.to_string();
+            let (kind, desired) = message
+                .strip_prefix("resource limit exceeded: ")?
+                .split_once(" count too high at ")?;
+            let desired = desired.trim().parse().ok()?;
+
+            match kind {
+                "instance" => Some(Self::Instances {
+                    desired,
+                    limit: limits.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT),
+                }),
+                "table" => Some(Self::Tables {
+                    desired,
+                    limit: limits.max_tables.unwrap_or(DEFAULT_TABLE_LIMIT),
+                }),
+                _ => None,
+            }
+        })
+    }
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory { desired, limit } => write!(
+                f,
+                "Memory limit exceeded: tried to grow to {} bytes, limit is {} bytes",
+                desired, limit
+            ),
+            Self::TableElements { desired, limit } => write!(
+                f,
+                "Table limit exceeded: tried to grow to {} elements, limit is {} elements",
+                desired, limit
+            ),
+            Self::Instances { desired, limit } => write!(
+                f,
+                "Instance limit exceeded: tried to create {} instances, limit is {} instances",
+                desired, limit
+            ),
+            Self::Tables { desired, limit } => write!(
+                f,
+                "Table count limit exceeded: tried to create {} tables, limit is {} tables",
+                desired, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResourceLimitExceeded {}
+
+/// `ResourceLimiter` enforcing `ResourceLimits` on a store.
+#[derive(Debug, Default)]
+pub struct StoreLimiter {
+    limits: ResourceLimits,
+    /// Total size in bytes of all linear memories in the store.
+    memory_size: usize,
+}
+
+impl StoreLimiter {
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self {
+            limits,
+            memory_size: 0,
+        }
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+}
+
+impl ResourceLimiter for StoreLimiter {
+    fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        match self.limits.max_memory {
+            Some(limit) if desired > limit => Err(ResourceLimitExceeded::Memory { desired, limit }.into()),
+            _ => {
+                self.memory_size += desired - current;
+                Ok(true)
+            }
+        }
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        match self.limits.max_table_elements {
+            Some(limit) if desired > limit => Err(ResourceLimitExceeded::TableElements { desired, limit }.into()),
+            _ => Ok(true),
+        }
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT)
+    }
+
+    fn tables(&self) -> usize {
+        self.limits.max_tables.unwrap_or(DEFAULT_TABLE_LIMIT)
+    }
+
+    fn memories(&self) -> usize {
+        DEFAULT_MEMORY_LIMIT
+    }
+}
@@ -0,0 +1,267 @@
+//! Pool of warm component instances.
+//!
+//! Each app keeps up to `size` instances. A call checks out an idle instance
+//! (or creates one while the pool isn't full, or waits for one to be checked
+//! back in, up to the call's deadline), and the instance is recycled instead of returned when it
+//! trapped, served `max_invocations` calls or grew past `max_memory` bytes.
+
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+/// Size and recycling policy of an `InstancePool`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolOptions {
+    /// Maximum number of instances, idle or checked out.
+    pub size: usize,
+    /// Recycle an instance after it served this many calls.
+    pub max_invocations: Option<u64>,
+    /// Recycle an instance once its linear memory exceeds this many bytes.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            max_invocations: None,
+            max_memory: None,
+        }
+    }
+}
+
+/// An instance checked out of the pool.
+pub struct Pooled<T> {
+    pub instance: T,
+    invocations: u64,
+}
+
+impl<T> Pooled<T> {
+    fn new(instance: T) -> Self {
+        Self {
+            instance,
+            invocations: 0,
+        }
+    }
+}
+
+struct PoolState<T> {
+    idle: Vec<Pooled<T>>,
+    /// Instances that exist, idle or checked out.
+    live: usize,
+}
+
+pub struct InstancePool<T> {
+    options: PoolOptions,
+    state: Mutex<PoolState<T>>,
+    returned: Condvar,
+}
+
+impl<T> Default for InstancePool<T> {
+    fn default() -> Self {
+        Self::new(PoolOptions::default(), Vec::new())
+    }
+}
+
+impl<T> InstancePool<T> {
+    /// Creates a pool holding the given warm instances.
+    pub fn new(options: PoolOptions, instances: Vec<T>) -> Self {
+        let options = PoolOptions {
+            size: options.size.max(1),
+            ..options
+        };
+
+        Self {
+            options,
+            state: Mutex::new(PoolState {
+                live: instances.len(),
+                idle: instances.into_iter().map(Pooled::new).collect(),
+            }),
+            returned: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes an idle instance. When there is none, creates one with `create`
+    /// if the pool isn't full, and otherwise blocks until one is checked in.
+    /// Returns `None` when `deadline` passes before an instance is free.
+    pub fn checkout<E>(
+        &self,
+        deadline: Option<Instant>,
+        create: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Option<Pooled<T>>, E> {
+        let mut state = self.lock();
+
+        loop {
+            if let Some(pooled) = state.idle.pop() {
+                return Ok(Some(pooled));
+            }
+            if state.live < self.options.size {
+                break;
+            }
+            state = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Ok(None);
+                    }
+                    self.returned
+                        .wait_timeout(state, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self.returned.wait(state).unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+
+        // Reserve the slot and instantiate without holding the lock.
+        state.live += 1;
+        drop(state);
+
+        create().map(|instance| Some(Pooled::new(instance))).inspect_err(|_| {
+            self.lock().live -= 1;
+            self.returned.notify_one();
+        })
+    }
+
+    /// Returns an instance after a call. It is dropped instead when it
+    /// `trapped` or when the recycling policy says it has served its time,
+    /// and a fresh one is created on a later checkout.
+    pub fn checkin(&self, mut pooled: Pooled<T>, trapped: bool, memory_size: usize) {
+        pooled.invocations += 1;
+
+        let worn_out = self.options.max_invocations.is_some_and(|max| pooled.invocations >= max);
+        let bloated = self.options.max_memory.is_some_and(|max| memory_size > max);
+
+        let mut state = self.lock();
+        if trapped || worn_out || bloated {
+            state.live -= 1;
+        } else {
+            state.idle.push(pooled);
+        }
+        drop(state);
+
+        self.returned.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn counter() -> impl Fn() -> Result<u32, Infallible> {
+        let next = AtomicU32::new(0);
+        move || Ok(next.fetch_add(1, Ordering::SeqCst))
+    }
+
+    /// Checks out an instance without a deadline.
+    fn checkout(pool: &InstancePool<u32>, create: impl FnOnce() -> Result<u32, Infallible>) -> Pooled<u32> {
+        pool.checkout(None, create).unwrap().unwrap()
+    }
+
+    #[test]
+    fn reuses_returned_instances() {
+        let pool = InstancePool::new(PoolOptions::default(), vec![7]);
+        let create = counter();
+
+        let pooled = checkout(&pool, &create);
+        assert_eq!(pooled.instance, 7);
+        pool.checkin(pooled, false, 0);
+
+        assert_eq!(checkout(&pool, &create).instance, 7);
+    }
+
+    #[test]
+    fn creates_instances_up_to_size() {
+        let options = PoolOptions {
+            size: 2,
+            ..Default::default()
+        };
+        let pool = InstancePool::new(options, Vec::new());
+        let create = counter();
+
+        let first = checkout(&pool, &create);
+        let second = checkout(&pool, &create);
+
+        assert_eq!((first.instance, second.instance), (0, 1));
+    }
+
+    #[test]
+    fn recycles_after_max_invocations() {
+        let options = PoolOptions {
+            max_invocations: Some(2),
+            ..Default::default()
+        };
+        let pool = InstancePool::new(options, Vec::new());
+        let create = counter();
+
+        for _ in 0..2 {
+            let pooled = checkout(&pool, &create);
+            assert_eq!(pooled.instance, 0);
+            pool.checkin(pooled, false, 0);
+        }
+
+        assert_eq!(checkout(&pool, &create).instance, 1);
+    }
+
+    #[test]
+    fn recycles_after_trap_or_memory_growth() {
+        let options = PoolOptions {
+            max_memory: Some(1024),
+            ..Default::default()
+        };
+        let pool = InstancePool::new(options, Vec::new());
+        let create = counter();
+
+        let pooled = checkout(&pool, &create);
+        pool.checkin(pooled, true, 0);
+        let pooled = checkout(&pool, &create);
+        assert_eq!(pooled.instance, 1);
+
+        pool.checkin(pooled, false, 2048);
+        assert_eq!(checkout(&pool, &create).instance, 2);
+    }
+
+    #[test]
+    fn waits_for_an_instance_when_full() {
+        let pool = Arc::new(InstancePool::new(PoolOptions::default(), vec![7]));
+        let pooled = checkout(&pool, counter());
+
+        let waiter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || checkout(&pool, counter()).instance)
+        };
+        thread::sleep(Duration::from_millis(50));
+        pool.checkin(pooled, false, 0);
+
+        assert_eq!(waiter.join().unwrap(), 7);
+    }
+
+    #[test]
+    fn gives_up_waiting_at_the_deadline() {
+        let pool = InstancePool::new(PoolOptions::default(), vec![7]);
+        let _pooled = checkout(&pool, counter());
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(50);
+        assert!(pool.checkout(Some(deadline), counter()).unwrap().is_none());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        assert!(pool.checkout(Some(started), counter()).unwrap().is_none());
+    }
+
+    #[test]
+    fn failed_creation_frees_the_slot() {
+        let pool: InstancePool<u32> = InstancePool::default();
+
+        assert!(pool.checkout(None, || Err("boom")).is_err());
+        assert_eq!(checkout(&pool, counter()).instance, 0);
+    }
+}
@@ -0,0 +1,95 @@
+//! Process-wide registry of the engine, linker and loaded components.
+//!
+//! Apps with the same `EngineOptions` share one `Engine` and `Linker`, and
+//! apps created for the same component share one compiled `Component` and
+//! `InstancePre`.
+//! Components are held weakly, so they are freed once the last app using
+//! them is garbage collected.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use wasmtime::component::Linker;
+use wasmtime::{Engine, Result};
+
+use crate::app_state::AppState;
+use crate::artifact_cache::{cache_key, load_component};
+use crate::component::{build_engine, build_linker, start_epoch_ticker, BridgePreWrapper, EngineOptions};
+
+pub struct Runtime {
+    pub engine: Engine,
+    pub linker: Linker<AppState>,
+}
+
+/// Runtimes are created once per distinct `EngineOptions` and live for the
+/// rest of the process.
+static RUNTIMES: LazyLock<Mutex<HashMap<EngineOptions, &'static Runtime>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Loaded components keyed by the digest of their wasm bytes.
+static COMPONENTS: LazyLock<Mutex<HashMap<String, Weak<BridgePreWrapper>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the shared engine and linker for `options`, building them on
+/// first use.
+pub fn runtime(options: EngineOptions) -> Result<&'static Runtime> {
+    let mut runtimes = RUNTIMES.lock().unwrap();
+    if let Some(runtime) = runtimes.get(&options) {
+        return Ok(runtime);
+    }
+
+    let engine = build_engine(options)?;
+    let linker = build_linker(&engine)?;
+    start_epoch_ticker(&engine);
+
+    let runtime: &'static Runtime = Box::leak(Box::new(Runtime { engine, linker }));
+    runtimes.insert(options, runtime);
+
+    Ok(runtime)
+}
+
+/// Returns the pre-linked component at `file_path` for `runtime`, compiling
+/// it only if no live app has loaded the same wasm bytes before.
+pub fn component(runtime: &Runtime, file_path: &str, cache_dir: Option<&Path>) -> Result<Arc<BridgePreWrapper>> {
+    let wasm = fs::read(file_path)?;
+    let key = cache_key(&runtime.engine, &wasm);
+
+    if let Some(pre) = lookup(&key) {
+        return Ok(pre);
+    }
+
+    // Compile outside the lock so other components can load concurrently.
+    let component = load_component(&runtime.engine, &wasm, &key, cache_dir)?;
+    let pre = Arc::new(BridgePreWrapper::new(&runtime.linker, &component)?);
+
+    let mut components = COMPONENTS.lock().unwrap();
+    components.retain(|_, weak| weak.strong_count() > 0);
+
+    // Another thread may have loaded the same component in the meantime.
+    if let Some(existing) = components.get(&key).and_then(Weak::upgrade) {
+        return Ok(existing);
+    }
+    components.insert(key, Arc::downgrade(&pre));
+
+    Ok(pre)
+}
+
+fn lookup(key: &str) -> Option<Arc<BridgePreWrapper>> {
+    COMPONENTS.lock().unwrap().get(key).and_then(Weak::upgrade)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_is_shared_per_engine_options() {
+        let first = runtime(EngineOptions::default()).unwrap();
+        let second = runtime(EngineOptions::default()).unwrap();
+        let fuel = runtime(EngineOptions { fuel: true }).unwrap();
+
+        assert!(Engine::same(&first.engine, &second.engine));
+        assert!(!Engine::same(&first.engine, &fuel.engine));
+    }
+}
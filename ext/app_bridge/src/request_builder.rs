@@ -1,26 +1,269 @@
 use crate::app_state::AppState;
-use crate::component::{v3, v4, v4_1};
-use crate::component::v4::standout::app::http::{Method, Request, RequestError};
-use reqwest::Method as ReqwestMethod;
+use crate::batch::map_concurrently;
+use crate::component::{v3, v4, v4_1, v4_2};
+use crate::component::v4::standout::app::http::{Method, Request};
+use crate::form::{encode_form, encode_multipart, FormPart};
+use crate::http_cache::CacheLookup;
+use crate::retry::RetryPolicy;
+use crate::transport::{HttpRequest, HttpResponse, HttpSender, RequestError};
+use reqwest::Url;
 use std::result::Result::Ok;
+use std::sync::Arc;
+use std::time::Duration;
 use wasmtime::component::Resource;
 
+// ============================================================================
+// Host-side request builder state
+// ============================================================================
+
+/// What a request builder has been given. Builder methods don't change a
+/// builder but derive a new one, so the URL, headers and body are shared
+/// between the two instead of copied: a method call costs a few reference
+/// counts however large the body is, and the body is freed with the last
+/// builder that holds it.
+#[derive(Debug, Clone)]
+pub struct BuilderState {
+    method: HttpMethod,
+    url: Arc<str>,
+    /// Query parameters appended to the URL when the request is built.
+    query: Arc<[(String, String)]>,
+    headers: Arc<[(String, String)]>,
+    body: RequestBody,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+}
+
+/// A request method: one of the verbs every version declares, or any other
+/// in versions with `method.other`.
+#[derive(Debug, Clone)]
+enum HttpMethod {
+    Known(Method),
+    Other(Arc<str>),
+}
+
+#[derive(Debug, Clone)]
+enum RequestBody {
+    Text(Arc<str>),
+    Bytes(Arc<[u8]>),
+    /// Fields encoded as `application/x-www-form-urlencoded` when sent.
+    Form(Arc<[(String, String)]>),
+    /// Parts encoded as `multipart/form-data` when sent.
+    Multipart(Arc<[FormPart]>),
+}
+
+impl Default for BuilderState {
+    fn default() -> Self {
+        Self {
+            method: HttpMethod::Known(Method::Get),
+            url: Arc::from(""),
+            query: Arc::new([]),
+            headers: Arc::new([]),
+            body: RequestBody::Text(Arc::from("")),
+            timeout: None,
+            retry: None,
+        }
+    }
+}
+
+impl BuilderState {
+    fn add_headers(&mut self, headers: impl IntoIterator<Item = (String, String)>) {
+        self.headers = self.headers.iter().cloned().chain(headers).collect();
+    }
+
+    fn add_query(&mut self, query: impl IntoIterator<Item = (String, String)>) {
+        self.query = self.query.iter().cloned().chain(query).collect();
+    }
+
+    /// The URL with the query parameters appended, percent-encoded. A URL
+    /// that doesn't parse is left as is for the transport to report.
+    fn full_url(&self) -> String {
+        if self.query.is_empty() {
+            return self.url.to_string();
+        }
+        match Url::parse(&self.url) {
+            Ok(mut url) => {
+                url.query_pairs_mut().extend_pairs(self.query.iter());
+                url.into()
+            }
+            Err(_) => self.url.to_string(),
+        }
+    }
+
+    /// The body as the http interface describes it. Binary and multipart
+    /// bodies show up as an empty string.
+    fn body_text(&self) -> String {
+        match &self.body {
+            RequestBody::Text(text) => text.to_string(),
+            RequestBody::Form(fields) => encode_form(fields),
+            RequestBody::Bytes(_) | RequestBody::Multipart(_) => String::new(),
+        }
+    }
+
+    /// The encoded body, and the `Content-Type` of form bodies.
+    fn encode_body(&self) -> Result<(Vec<u8>, Option<String>), RequestError> {
+        match &self.body {
+            RequestBody::Text(text) => Ok((text.as_bytes().to_vec(), None)),
+            RequestBody::Bytes(bytes) => Ok((bytes.to_vec(), None)),
+            RequestBody::Form(fields) => Ok((
+                encode_form(fields).into_bytes(),
+                Some("application/x-www-form-urlencoded".to_string()),
+            )),
+            RequestBody::Multipart(parts) => encode_multipart(parts)
+                .map(|(body, content_type)| (body, Some(content_type)))
+                .map_err(RequestError::Other),
+        }
+    }
+
+    /// The request as versions without `method.other` describe it. Their
+    /// components can't set another method, so `Get` never stands in for one.
+    fn to_request(&self) -> Request {
+        Request {
+            method: match &self.method {
+                HttpMethod::Known(method) => *method,
+                HttpMethod::Other(_) => Method::Get,
+            },
+            url: self.full_url(),
+            headers: self.headers.to_vec(),
+            body: self.body_text(),
+        }
+    }
+}
+
+impl AppState {
+    /// Stores `state` under a new builder id.
+    fn insert_builder<T: 'static>(&mut self, state: BuilderState) -> Resource<T> {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.request_builders.insert(id, state);
+        Resource::new_own(id)
+    }
+
+    /// Derives a new builder from builder `id`, changed by `change`.
+    fn derive_builder<T: 'static>(&mut self, id: u32, change: impl FnOnce(&mut BuilderState)) -> Resource<T> {
+        let mut state = self.request_builders.get(&id).cloned().unwrap_or_default();
+        change(&mut state);
+        self.insert_builder(state)
+    }
+
+    /// The request built by builder `id`, with the timeout and retry policy
+    /// it is sent with.
+    fn outgoing_request(&self, id: u32) -> Result<OutgoingRequest, RequestError> {
+        let state = self
+            .request_builders
+            .get(&id)
+            .ok_or_else(|| RequestError::Other("Request not found".to_string()))?;
+        let (body, content_type) = state.encode_body()?;
+        let mut headers = state.headers.to_vec();
+        // Form bodies replace the Content-Type the component set.
+        if let Some(content_type) = content_type {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+            headers.push(("Content-Type".to_string(), content_type));
+        }
+
+        let mut request = HttpRequest {
+            method: state.method.to_string(),
+            url: state.full_url(),
+            headers,
+            body,
+            timeout: None,
+            max_response_size: self.http.max_response_size,
+        };
+        let cache = self.cache_lookup(&mut request);
+
+        Ok(OutgoingRequest {
+            request,
+            timeout: state.timeout,
+            retry: state.retry.or(self.http.retry),
+            cache,
+        })
+    }
+
+    fn send_built_request(&self, id: u32) -> Result<Response, RequestError> {
+        let sent = self.outgoing_request(id)?.send(self.http_sender())?;
+        Ok(self.receive(sent))
+    }
+
+    /// Sends the requests of builders `ids`, up to `parallelism` at a time,
+    /// and returns their results in the same order.
+    fn send_built_requests(&self, ids: &[u32], parallelism: u32) -> Vec<Result<Response, RequestError>> {
+        let requests = ids.iter().map(|&id| self.outgoing_request(id)).collect();
+        let parallelism = if self.transport.is_concurrent() {
+            self.http.parallelism_for(parallelism)
+        } else {
+            1
+        };
+        let sender = self.http_sender();
+
+        map_concurrently(requests, parallelism, |request: Result<OutgoingRequest, RequestError>| {
+            request?.send(sender)
+        })
+        .into_iter()
+        .map(|sent| sent.map(|sent| self.receive(sent)))
+        .collect()
+    }
+
+    /// The response to a sent request. The cache is only consulted here and
+    /// in `outgoing_request`, on the invoking thread, since a Ruby store
+    /// can't be called from the threads a batch is sent on.
+    fn receive(&self, sent: SentRequest) -> Response {
+        let response = match sent.cache {
+            Some(lookup) => self.cache_response(lookup, sent.response),
+            None => sent.response,
+        };
+        Response {
+            attempts: sent.attempts,
+            ..response.into()
+        }
+    }
+}
+
+/// A built request and the host-side settings it is sent with.
+struct OutgoingRequest {
+    request: HttpRequest,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    cache: Option<CacheLookup>,
+}
+
+/// The last response to an `OutgoingRequest`.
+struct SentRequest {
+    response: HttpResponse,
+    attempts: u32,
+    cache: Option<CacheLookup>,
+}
+
+impl OutgoingRequest {
+    fn send(self, sender: HttpSender<'_>) -> Result<SentRequest, RequestError> {
+        let (response, attempts) = sender.send_with_retries(self.request, self.timeout, self.retry)?;
+        Ok(SentRequest {
+            response,
+            attempts,
+            cache: self.cache,
+        })
+    }
+}
+
 // ============================================================================
 // Macro to implement HostRequestBuilder for any version
 //
 // When adding a new version, just add:
-//   impl_host_request_builder!(v5);
-//   impl_http_type_conversions!(v5);
+//   impl_host_request_builder!(v5, body_bytes, timeout, retry, query, form);
+//   impl_http_host!(v5, send_all);
+//   impl_http_type_conversions!(v5, other_method);
+//   impl_request_error_conversion!(v5, structured);
+// listing the optional builder methods and functions the version's WIT
+// declares.
 // ============================================================================
 
 macro_rules! impl_host_request_builder {
-    ($v:ident, $has_body_bytes:ident) => {
+    ($v:ident $(, $extra:ident)*) => {
         impl $v::standout::app::http::HostRequestBuilder for AppState {
             fn new(&mut self) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self.next_request_id;
-                self.next_request_id += 1;
-                self.request_list.insert(id, Request::default());
-                Resource::new_own(id)
+                let headers = [("User-Agent".to_string(), self.http.user_agent())];
+                self.insert_builder(BuilderState {
+                    headers: Arc::new(headers),
+                    ..Default::default()
+                })
             }
 
             fn method(
@@ -28,19 +271,7 @@ macro_rules! impl_host_request_builder {
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
                 method: $v::standout::app::http::Method,
             ) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self_.rep();
-                if let Some(mut request) = self.request_list.get(&id).cloned() {
-                    request.method = method.into();
-                    let new_id = self.next_request_id;
-                    self.next_request_id += 1;
-                    self.request_list.insert(new_id, request);
-                    if let Some(bytes) = self.request_body_bytes.get(&id).cloned() {
-                        self.request_body_bytes.insert(new_id, bytes);
-                    }
-                    Resource::new_own(new_id)
-                } else {
-                    Resource::new_own(id)
-                }
+                self.derive_builder(self_.rep(), |state| state.method = method.into())
             }
 
             fn url(
@@ -48,19 +279,7 @@ macro_rules! impl_host_request_builder {
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
                 url: String,
             ) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self_.rep();
-                if let Some(mut request) = self.request_list.get(&id).cloned() {
-                    request.url = url;
-                    let new_id = self.next_request_id;
-                    self.next_request_id += 1;
-                    self.request_list.insert(new_id, request);
-                    if let Some(bytes) = self.request_body_bytes.get(&id).cloned() {
-                        self.request_body_bytes.insert(new_id, bytes);
-                    }
-                    Resource::new_own(new_id)
-                } else {
-                    Resource::new_own(id)
-                }
+                self.derive_builder(self_.rep(), |state| state.url = url.into())
             }
 
             fn header(
@@ -69,16 +288,7 @@ macro_rules! impl_host_request_builder {
                 key: String,
                 value: String,
             ) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self_.rep();
-                let mut request = self.request_list.get(&id).cloned().unwrap_or_default();
-                request.headers.push((key, value));
-                let new_id = self.next_request_id;
-                self.next_request_id += 1;
-                self.request_list.insert(new_id, request);
-                if let Some(bytes) = self.request_body_bytes.get(&id).cloned() {
-                    self.request_body_bytes.insert(new_id, bytes);
-                }
-                Resource::new_own(new_id)
+                self.derive_builder(self_.rep(), |state| state.add_headers([(key, value)]))
             }
 
             fn headers(
@@ -86,16 +296,7 @@ macro_rules! impl_host_request_builder {
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
                 headers: Vec<(String, String)>,
             ) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self_.rep();
-                let mut request = self.request_list.get(&id).cloned().unwrap_or_default();
-                request.headers.extend(headers);
-                let new_id = self.next_request_id;
-                self.next_request_id += 1;
-                self.request_list.insert(new_id, request);
-                if let Some(bytes) = self.request_body_bytes.get(&id).cloned() {
-                    self.request_body_bytes.insert(new_id, bytes);
-                }
-                Resource::new_own(new_id)
+                self.derive_builder(self_.rep(), |state| state.add_headers(headers))
             }
 
             fn body(
@@ -103,40 +304,23 @@ macro_rules! impl_host_request_builder {
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
                 body: String,
             ) -> Resource<$v::standout::app::http::RequestBuilder> {
-                let id = self_.rep();
-                let mut request = self.request_list.get(&id).cloned().unwrap_or_default();
-                request.body = body;
-                let new_id = self.next_request_id;
-                self.next_request_id += 1;
-                self.request_list.insert(new_id, request);
-                self.request_body_bytes.remove(&new_id);
-                Resource::new_own(new_id)
+                self.derive_builder(self_.rep(), |state| state.body = RequestBody::Text(body.into()))
             }
 
             fn send(
                 &mut self,
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
             ) -> Result<$v::standout::app::http::Response, $v::standout::app::http::RequestError> {
-                let id = self_.rep();
-                match self.request_list.get(&id).cloned() {
-                    Some(request) => {
-                        let body_bytes = self.request_body_bytes.get(&id).map(|b| b.as_slice());
-                        send_request(&self.client, &request, body_bytes)
-                            .map(Into::into)
-                            .map_err(Into::into)
-                    }
-                    None => Err($v::standout::app::http::RequestError::Other(
-                        "Request not found".to_string(),
-                    )),
-                }
+                self.send_built_request(self_.rep())
+                    .map(Into::into)
+                    .map_err(Into::into)
             }
 
             fn drop(
                 &mut self,
                 rep: Resource<$v::standout::app::http::RequestBuilder>,
             ) -> wasmtime::Result<()> {
-                self.request_list.remove(&rep.rep());
-                self.request_body_bytes.remove(&rep.rep());
+                self.request_builders.remove(&rep.rep());
                 Ok(())
             }
 
@@ -144,38 +328,128 @@ macro_rules! impl_host_request_builder {
                 &mut self,
                 self_: Resource<$v::standout::app::http::RequestBuilder>,
             ) -> $v::standout::app::http::Request {
-                self.request_list
-                    .get(&self_.rep())
-                    .cloned()
-                    .unwrap_or_default()
-                    .into()
+                let state = self.request_builders.get(&self_.rep()).cloned().unwrap_or_default();
+                (&state).into()
             }
 
-            impl_host_request_builder_body_bytes!($v, $has_body_bytes);
+            $(impl_host_request_builder_extra!($v, $extra);)*
         }
     };
 }
 
-macro_rules! impl_host_request_builder_body_bytes {
-    ($v:ident, yes) => {
+/// Builder methods that only some WIT versions declare.
+macro_rules! impl_host_request_builder_extra {
+    ($v:ident, body_bytes) => {
         fn body_bytes(
             &mut self,
             self_: Resource<$v::standout::app::http::RequestBuilder>,
             body: Vec<u8>,
         ) -> Resource<$v::standout::app::http::RequestBuilder> {
-            let id = self_.rep();
-            let mut request = self.request_list.get(&id).cloned().unwrap_or_default();
-            request.body.clear();
-            let new_id = self.next_request_id;
-            self.next_request_id += 1;
-            self.request_list.insert(new_id, request);
-            self.request_body_bytes.insert(new_id, body);
-            Resource::new_own(new_id)
+            self.derive_builder(self_.rep(), |state| state.body = RequestBody::Bytes(body.into()))
+        }
+    };
+    ($v:ident, form) => {
+        fn form(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            fields: Vec<(String, String)>,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            self.derive_builder(self_.rep(), |state| state.body = RequestBody::Form(fields.into()))
+        }
+
+        fn multipart(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            parts: Vec<$v::standout::app::http::MultipartPart>,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            use $v::standout::app::http::MultipartPart;
+
+            let parts: Arc<[FormPart]> = parts
+                .into_iter()
+                .map(|part| match part {
+                    MultipartPart::Text(field) => FormPart::Text {
+                        name: field.name,
+                        value: field.value,
+                    },
+                    MultipartPart::File(file) => FormPart::File {
+                        name: file.name,
+                        filename: file.file.filename,
+                        content_type: file.file.content_type,
+                        base64: file.file.base64,
+                    },
+                })
+                .collect();
+            self.derive_builder(self_.rep(), |state| state.body = RequestBody::Multipart(parts))
+        }
+    };
+    ($v:ident, timeout) => {
+        fn timeout(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            milliseconds: u32,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            self.derive_builder(self_.rep(), |state| {
+                state.timeout = Some(Duration::from_millis(milliseconds.into()))
+            })
+        }
+    };
+    ($v:ident, query) => {
+        fn query(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            name: String,
+            value: String,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            self.derive_builder(self_.rep(), |state| state.add_query([(name, value)]))
+        }
+
+        fn queries(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            params: Vec<(String, String)>,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            self.derive_builder(self_.rep(), |state| state.add_query(params))
+        }
+    };
+    ($v:ident, retry) => {
+        fn retry(
+            &mut self,
+            self_: Resource<$v::standout::app::http::RequestBuilder>,
+            policy: $v::standout::app::http::RetryPolicy,
+        ) -> Resource<$v::standout::app::http::RequestBuilder> {
+            self.derive_builder(self_.rep(), |state| {
+                state.retry = Some(RetryPolicy {
+                    max_attempts: policy.max_attempts,
+                    initial_backoff: Duration::from_millis(policy.initial_backoff_ms.into()),
+                    max_backoff: Duration::from_millis(policy.max_backoff_ms.into()),
+                })
+            })
         }
     };
-    ($v:ident, no) => {};
 }
 
+/// Implements the functions of the http interface, `send_all` for versions
+/// that declare it.
+macro_rules! impl_http_host {
+    ($v:ident) => {
+        impl $v::standout::app::http::Host for AppState {}
+    };
+    ($v:ident, send_all) => {
+        impl $v::standout::app::http::Host for AppState {
+            fn send_all(
+                &mut self,
+                requests: Vec<Resource<$v::standout::app::http::RequestBuilder>>,
+                max_parallel: u32,
+            ) -> Vec<Result<$v::standout::app::http::Response, $v::standout::app::http::RequestError>> {
+                let ids: Vec<u32> = requests.iter().map(Resource::rep).collect();
+                self.send_built_requests(&ids, max_parallel)
+                    .into_iter()
+                    .map(|result| result.map(Into::into).map_err(Into::into))
+                    .collect()
+            }
+        }
+    };
+}
 
 // ============================================================================
 // Macro to implement HTTP type conversions for a version
@@ -198,6 +472,12 @@ macro_rules! impl_http_type_conversions {
             }
         }
 
+        impl From<$v::standout::app::http::Method> for HttpMethod {
+            fn from(m: $v::standout::app::http::Method) -> Self {
+                Self::Known(m.into())
+            }
+        }
+
         impl From<Method> for $v::standout::app::http::Method {
             fn from(m: Method) -> Self {
                 match m {
@@ -212,8 +492,9 @@ macro_rules! impl_http_type_conversions {
             }
         }
 
-        impl From<Request> for $v::standout::app::http::Request {
-            fn from(r: Request) -> Self {
+        impl From<&BuilderState> for $v::standout::app::http::Request {
+            fn from(state: &BuilderState) -> Self {
+                let r = state.to_request();
                 Self {
                     method: r.method.into(),
                     url: r.url,
@@ -222,18 +503,93 @@ macro_rules! impl_http_type_conversions {
                 }
             }
         }
+    };
+    ($v:ident, other_method) => {
+        impl From<$v::standout::app::http::Method> for HttpMethod {
+            fn from(m: $v::standout::app::http::Method) -> Self {
+                use $v::standout::app::http::Method as V;
+                Self::Known(match m {
+                    V::Get => Method::Get,
+                    V::Post => Method::Post,
+                    V::Put => Method::Put,
+                    V::Delete => Method::Delete,
+                    V::Patch => Method::Patch,
+                    V::Options => Method::Options,
+                    V::Head => Method::Head,
+                    V::Other(method) => return Self::Other(method.into()),
+                })
+            }
+        }
 
+        impl From<HttpMethod> for $v::standout::app::http::Method {
+            fn from(m: HttpMethod) -> Self {
+                match m {
+                    HttpMethod::Known(Method::Get) => Self::Get,
+                    HttpMethod::Known(Method::Post) => Self::Post,
+                    HttpMethod::Known(Method::Put) => Self::Put,
+                    HttpMethod::Known(Method::Delete) => Self::Delete,
+                    HttpMethod::Known(Method::Patch) => Self::Patch,
+                    HttpMethod::Known(Method::Options) => Self::Options,
+                    HttpMethod::Known(Method::Head) => Self::Head,
+                    HttpMethod::Other(method) => Self::Other(method.to_string()),
+                }
+            }
+        }
+
+        impl From<&BuilderState> for $v::standout::app::http::Request {
+            fn from(state: &BuilderState) -> Self {
+                Self {
+                    method: state.method.clone().into(),
+                    url: state.full_url(),
+                    headers: state.headers.to_vec(),
+                    body: state.body_text(),
+                }
+            }
+        }
+    };
+}
+
+/// Versions without structured request errors receive `other(msg)`.
+macro_rules! impl_request_error_conversion {
+    ($v:ident, structured) => {
         impl From<RequestError> for $v::standout::app::http::RequestError {
             fn from(e: RequestError) -> Self {
                 match e {
+                    RequestError::Timeout(msg) => Self::Timeout(msg),
+                    RequestError::Connect(msg) => Self::Connect(msg),
+                    RequestError::Dns(msg) => Self::Dns(msg),
+                    RequestError::Tls(msg) => Self::Tls(msg),
+                    RequestError::TooManyRedirects(msg) => Self::TooManyRedirects(msg),
+                    RequestError::BodyTooLarge(msg) => Self::BodyTooLarge(msg),
+                    RequestError::Forbidden(msg) => Self::Forbidden(msg),
                     RequestError::Other(msg) => Self::Other(msg),
                 }
             }
         }
     };
+    ($v:ident, other_only) => {
+        impl From<RequestError> for $v::standout::app::http::RequestError {
+            fn from(e: RequestError) -> Self {
+                Self::Other(e.into_message())
+            }
+        }
+    };
 }
 
 macro_rules! impl_http_response_conversion {
+    ($v:ident, with_attempts) => {
+        impl From<Response> for $v::standout::app::http::Response {
+            fn from(r: Response) -> Self {
+                Self {
+                    status: r.status,
+                    headers: r.headers,
+                    body: r.body,
+                    body_bytes: r.body_bytes,
+                    attempts: r.attempts,
+                }
+            }
+        }
+    };
     ($v:ident, with_bytes) => {
         impl From<Response> for $v::standout::app::http::Response {
             fn from(r: Response) -> Self {
@@ -261,78 +617,52 @@ macro_rules! impl_http_response_conversion {
 
 // ============================================================================
 // Generate implementations for all supported versions
-// When adding v5, just add:
-//   impl_host_request_builder!(v5);
-//   impl_http_type_conversions!(v5);
 // ============================================================================
 
-impl_host_request_builder!(v3, no);
-impl_host_request_builder!(v4, no);
-impl_host_request_builder!(v4_1, yes);
+impl_host_request_builder!(v3);
+impl_host_request_builder!(v4);
+impl_host_request_builder!(v4_1, body_bytes);
+impl_host_request_builder!(v4_2, body_bytes, timeout, retry, query, form);
+
+impl_http_host!(v3);
+impl_http_host!(v4);
+impl_http_host!(v4_1);
+impl_http_host!(v4_2, send_all);
 
 impl_http_type_conversions!(v3);
 impl_http_type_conversions!(v4_1);
+impl_http_type_conversions!(v4_2, other_method);
+impl_request_error_conversion!(v3, other_only);
+impl_request_error_conversion!(v4, other_only);
+impl_request_error_conversion!(v4_1, other_only);
+impl_request_error_conversion!(v4_2, structured);
 impl_http_response_conversion!(v3, no_bytes);
 impl_http_response_conversion!(v4, no_bytes);
 impl_http_response_conversion!(v4_1, with_bytes);
+impl_http_response_conversion!(v4_2, with_attempts);
 
 // ============================================================================
-// Shared request sending logic
+// Response shared by all versions
 // ============================================================================
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct Response {
     status: u16,
     headers: Vec<(String, String)>,
     body: String,
     body_bytes: Option<Vec<u8>>,
+    attempts: u32,
 }
 
-fn send_request(
-    client: &std::sync::Arc<std::sync::Mutex<reqwest::blocking::Client>>,
-    request: &Request,
-    body_bytes: Option<&[u8]>,
-) -> Result<Response, RequestError> {
-    let client = client.lock().unwrap();
-    let mut builder = client.request(request.method.clone().into(), &request.url);
-
-    for (key, value) in &request.headers {
-        builder = builder.header(key, value);
-    }
-    builder = if let Some(bytes) = body_bytes {
-        builder.body(bytes.to_vec())
-    } else {
-        builder.body(request.body.clone())
-    };
-
-    match builder.send() {
-        Ok(resp) => {
-            let status = resp.status().as_u16();
-            let headers = resp
-                .headers()
-                .iter()
-                .map(|(k, v)| {
-                    (
-                        k.as_str().to_string(),
-                        v.to_str().unwrap_or_default().to_string(),
-                    )
-                })
-                .collect();
-
-            let bytes = resp.bytes().unwrap_or_default().to_vec();
-            let body = String::from_utf8(bytes.clone()).unwrap_or_default();
-
-            Ok(Response {
-                status,
-                headers,
-                body,
-                body_bytes: Some(bytes),
-            })
+impl From<HttpResponse> for Response {
+    fn from(response: HttpResponse) -> Self {
+        Self {
+            body: response.text(),
+            status: response.status,
+            headers: response.headers,
+            body_bytes: Some(response.body),
+            attempts: 1,
         }
-        Err(error) => Err(RequestError::Other(format!(
-            "Request failed to {} {}: {}",
-            request.method, request.url, error
-        ))),
     }
 }
 
@@ -340,41 +670,23 @@ fn send_request(
 // Standard type implementations (used by all versions)
 // ============================================================================
 
-impl From<Method> for ReqwestMethod {
+impl From<Method> for HttpMethod {
     fn from(method: Method) -> Self {
-        match method {
-            Method::Get => Self::GET,
-            Method::Post => Self::POST,
-            Method::Put => Self::PUT,
-            Method::Delete => Self::DELETE,
-            Method::Patch => Self::PATCH,
-            Method::Head => Self::HEAD,
-            Method::Options => Self::OPTIONS,
-        }
+        Self::Known(method)
     }
 }
 
-impl Default for Request {
-    fn default() -> Self {
-        Self {
-            url: String::new(),
-            method: Method::Get,
-            body: String::new(),
-            headers: vec![(
-                "User-Agent".to_string(),
-                format!("Standout-AppBridge/{}", env!("CARGO_PKG_VERSION")),
-            )],
-        }
+impl From<&BuilderState> for Request {
+    fn from(state: &BuilderState) -> Self {
+        state.to_request()
     }
 }
 
-impl Default for Response {
-    fn default() -> Self {
-        Self {
-            status: 0,
-            headers: Vec::new(),
-            body: String::new(),
-            body_bytes: None,
+impl std::fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Known(method) => method.fmt(f),
+            Self::Other(method) => f.write_str(method),
         }
     }
 }
@@ -400,7 +712,25 @@ impl std::fmt::Display for Method {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cassette::Cassette;
+    use crate::egress::EgressPolicy;
+    use crate::http_options::HttpOptions;
+    use crate::transport::ReqwestTransport;
     use httpmock::{Method::GET, Method::POST, MockServer};
+    use std::sync::Arc;
+    use wasmtime_wasi::WasiCtxBuilder;
+
+    /// App state whose egress policy allows the local mock servers.
+    fn local_app_state() -> AppState {
+        let http = HttpOptions {
+            egress: Arc::new(EgressPolicy {
+                allow_private_networks: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        AppState::new(WasiCtxBuilder::new().build(), None, http)
+    }
 
     #[test]
     fn sends_request_with_default_user_agent() {
@@ -418,7 +748,7 @@ mod tests {
         });
         let url = format!("{}/headers", server.base_url());
 
-        let mut app_state = AppState::default();
+        let mut app_state = local_app_state();
         let builder = app_state.new();
         let builder = app_state.method(builder, Method::Get);
         let builder = app_state.url(builder, url);
@@ -442,7 +772,7 @@ mod tests {
         });
         let url = format!("{}/upload", server.base_url());
 
-        let mut app_state = AppState::default();
+        let mut app_state = local_app_state();
         let builder = app_state.new();
         let builder = app_state.method(builder, v4_1::standout::app::http::Method::Post);
         let builder = app_state.url(builder, url);
@@ -469,7 +799,7 @@ mod tests {
         });
         let url = format!("{}/download", server.base_url());
 
-        let mut app_state = AppState::default();
+        let mut app_state = local_app_state();
         let builder = app_state.new();
         let builder = app_state.method(builder, v4_1::standout::app::http::Method::Post);
         let builder = app_state.url(builder, url);
@@ -480,4 +810,497 @@ mod tests {
         assert_eq!(response.body_bytes, Some(body));
         mock.assert();
     }
+
+    #[test]
+    fn request_timeout_returns_timeout_error() {
+        use v4_2::standout::app::http::{HostRequestBuilder, RequestError};
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/slow");
+            then.status(200).delay(std::time::Duration::from_millis(500));
+        });
+        let url = format!("{}/slow", server.base_url());
+
+        let mut app_state = local_app_state();
+        let builder = app_state.new();
+        let builder = app_state.url(builder, url);
+        let builder = app_state.timeout(builder, 50);
+
+        match app_state.send(builder) {
+            Err(RequestError::Timeout(message)) => assert!(message.contains("timed out after 50 ms")),
+            other => panic!("expected a timeout error, got {other:?}"),
+        }
+    }
+
+    fn send_v4_2(app_state: &mut AppState, url: String) -> Result<(), v4_2::standout::app::http::RequestError> {
+        use v4_2::standout::app::http::HostRequestBuilder;
+
+        let builder = app_state.new();
+        let builder = app_state.url(builder, url);
+        app_state.send(builder).map(|_| ())
+    }
+
+    #[test]
+    fn refused_connection_returns_connect_error() {
+        use v4_2::standout::app::http::RequestError;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let result = send_v4_2(&mut local_app_state(), url);
+        assert!(matches!(result, Err(RequestError::Connect(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn unresolvable_host_returns_dns_error() {
+        use v4_2::standout::app::http::RequestError;
+
+        let result = send_v4_2(&mut local_app_state(), "http://app-bridge.invalid/".to_string());
+        assert!(matches!(result, Err(RequestError::Dns(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn failed_handshake_returns_tls_error() {
+        use v4_2::standout::app::http::RequestError;
+
+        let server = MockServer::start();
+        let url = format!("https://{}/", server.address());
+
+        let result = send_v4_2(&mut local_app_state(), url);
+        assert!(matches!(result, Err(RequestError::Tls(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn redirect_loop_returns_too_many_redirects_error() {
+        use v4_2::standout::app::http::RequestError;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/loop");
+            then.status(302).header("Location", "/loop");
+        });
+
+        let result = send_v4_2(&mut local_app_state(), format!("{}/loop", server.base_url()));
+        assert!(matches!(result, Err(RequestError::TooManyRedirects(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn older_versions_receive_other_error() {
+        use v4_1::standout::app::http::{HostRequestBuilder, RequestError};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+
+        let mut app_state = local_app_state();
+        let builder = app_state.new();
+        let builder = app_state.url(builder, url);
+
+        assert!(matches!(app_state.send(builder), Err(RequestError::Other(_))));
+    }
+
+    #[test]
+    fn egress_policy_blocks_internal_addresses_by_default() {
+        use v4_2::standout::app::http::RequestError;
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET);
+            then.status(200);
+        });
+
+        let result = send_v4_2(&mut AppState::default(), server.url("/"));
+        assert!(matches!(&result, Err(RequestError::Forbidden(msg)) if msg.contains("internal address")), "got {result:?}");
+
+        let result = send_v4_2(&mut AppState::default(), format!("http://localhost:{}/", server.port()));
+        assert!(matches!(result, Err(RequestError::Forbidden(_))), "got {result:?}");
+        mock.assert_calls(0);
+    }
+
+    #[test]
+    fn egress_policy_checks_redirect_targets() {
+        use v4_2::standout::app::http::RequestError;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/redirect");
+            then.status(302).header("Location", "http://169.254.169.254/latest/meta-data");
+        });
+
+        let mut app_state = local_app_state();
+        app_state.http.egress = Arc::new(EgressPolicy {
+            allow_cidrs: vec!["127.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        });
+        app_state.transport = Arc::new(ReqwestTransport::new(&app_state.http.egress, &app_state.http.client).unwrap());
+
+        let result = send_v4_2(&mut app_state, format!("{}/redirect", server.base_url()));
+        assert!(matches!(result, Err(RequestError::Forbidden(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn replays_recorded_traffic_without_the_server() {
+        use crate::cassette::CassetteMode;
+        use v4_2::standout::app::http::HostRequestBuilder;
+
+        let path = std::env::temp_dir().join(format!("app_bridge_request_cassette_{}.json", std::process::id()));
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/items").body("{}");
+            then.status(201).body("created");
+        });
+        let url = format!("{}/items", server.base_url());
+
+        let send = |mode: CassetteMode| {
+            let mut app_state = local_app_state();
+            app_state.http.cassette = Some(Arc::new(Cassette::open(&path, mode).unwrap()));
+
+            let builder = app_state.new();
+            let builder = app_state.method(builder, v4_2::standout::app::http::Method::Post);
+            let builder = app_state.url(builder, url.clone());
+            let builder = app_state.body(builder, "{}".to_string());
+            app_state.send(builder)
+        };
+
+        assert_eq!(send(CassetteMode::Record).unwrap().status, 201);
+        drop(server);
+
+        let replayed = send(CassetteMode::Replay).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replayed.status, 201);
+        assert_eq!(replayed.body, "created");
+    }
+
+    #[test]
+    fn retries_rate_limited_requests() {
+        use v4_2::standout::app::http::{HostRequestBuilder, RetryPolicy};
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/limited");
+            then.status(429).header("Retry-After", "0");
+        });
+        let url = format!("{}/limited", server.base_url());
+
+        let mut app_state = local_app_state();
+        let builder = app_state.new();
+        let builder = app_state.url(builder, url);
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 10,
+        };
+        let builder = app_state.retry(builder, policy);
+
+        let response = app_state.send(builder).expect("Request failed");
+
+        assert_eq!((response.status, response.attempts), (429, 3));
+        mock.assert_calls(3);
+    }
+
+    #[test]
+    fn retries_stop_at_the_deadline() {
+        use v4_2::standout::app::http::HostRequestBuilder;
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/unavailable");
+            then.status(503);
+        });
+        let url = format!("{}/unavailable", server.base_url());
+
+        let mut app_state = local_app_state();
+        app_state.http.retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_secs(5),
+            ..Default::default()
+        });
+        app_state.deadline = Some(std::time::Instant::now() + Duration::from_secs(1));
+        let builder = app_state.new();
+        let builder = app_state.url(builder, url);
+
+        let response = app_state.send(builder).expect("Request failed");
+
+        assert_eq!((response.status, response.attempts), (503, 1));
+        mock.assert_calls(1);
+    }
+
+    #[test]
+    fn response_larger_than_the_limit_returns_body_too_large() {
+        use v4_2::standout::app::http::RequestError;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/export");
+            then.status(200).body("x".repeat(2048));
+        });
+
+        let mut app_state = local_app_state();
+        app_state.http.max_response_size = Some(1024);
+
+        let result = send_v4_2(&mut app_state, format!("{}/export", server.base_url()));
+        assert!(matches!(result, Err(RequestError::BodyTooLarge(_))), "got {result:?}");
+    }
+
+    #[test]
+    fn non_utf8_body_is_decoded_for_versions_without_body_bytes() {
+        use v4::standout::app::http::HostRequestBuilder;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/latin1");
+            then.status(200)
+                .header("Content-Type", "text/csv; charset=windows-1252")
+                .body(b"name\nJos\xe9");
+        });
+
+        let mut app_state = local_app_state();
+        let builder = app_state.new();
+        let builder = app_state.url(builder, format!("{}/latin1", server.base_url()));
+
+        let response = app_state.send(builder).expect("Request failed");
+        assert_eq!(response.body, "name\nJosé");
+    }
+
+    #[test]
+    fn send_all_returns_results_in_order() {
+        use v4_2::standout::app::http::{Host, HostRequestBuilder, RequestError};
+
+        let server = MockServer::start();
+        for (path, delay) in [("/slow", 300), ("/fast", 0)] {
+            server.mock(|when, then| {
+                when.method(GET).path(path);
+                then.status(200).body(path).delay(Duration::from_millis(delay));
+            });
+        }
+
+        let mut app_state = local_app_state();
+        let mut builders = Vec::new();
+        for url in [
+            format!("{}/slow", server.base_url()),
+            "http://localhost:1/refused".to_string(),
+            format!("{}/fast", server.base_url()),
+        ] {
+            let builder = app_state.new();
+            builders.push(app_state.url(builder, url));
+        }
+
+        let started = std::time::Instant::now();
+        let results = app_state.send_all(builders, 3);
+
+        assert!(started.elapsed() < Duration::from_millis(600));
+        assert_eq!(results[0].as_ref().map(|r| r.body.as_str()).ok(), Some("/slow"));
+        assert!(matches!(results[1], Err(RequestError::Connect(_))), "got {:?}", results[1]);
+        assert_eq!(results[2].as_ref().map(|r| r.body.as_str()).ok(), Some("/fast"));
+        assert_eq!(app_state.take_http_log().len(), 3);
+    }
+
+    #[test]
+    fn send_all_is_sequential_for_transports_bound_to_the_invoking_thread() {
+        use crate::transport::Transport;
+        use v4_2::standout::app::http::{Host, HostRequestBuilder};
+
+        #[derive(Debug)]
+        struct CurrentThreadTransport(std::thread::ThreadId);
+
+        impl Transport for CurrentThreadTransport {
+            fn send(&self, _request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+                assert_eq!(std::thread::current().id(), self.0);
+                Ok(HttpResponse { status: 204, ..Default::default() })
+            }
+
+            fn is_concurrent(&self) -> bool {
+                false
+            }
+        }
+
+        let mut app_state = AppState::default();
+        app_state.transport = Arc::new(CurrentThreadTransport(std::thread::current().id()));
+        let builders = (0..4)
+            .map(|_| {
+                let builder = app_state.new();
+                app_state.url(builder, "https://93.184.215.14/".to_string())
+            })
+            .collect();
+
+        let results = app_state.send_all(builders, 4);
+        assert!(results.iter().all(|result| result.as_ref().is_ok_and(|r| r.status == 204)));
+    }
+
+    #[test]
+    fn chained_builders_share_the_body() {
+        use v4_1::standout::app::http::HostRequestBuilder;
+
+        let mut app_state = AppState::default();
+        let builder = app_state.new();
+        let with_body = app_state.body_bytes(builder, vec![0; 1024 * 1024]);
+        let with_header = app_state.header(
+            Resource::new_borrow(with_body.rep()),
+            "Content-Type".to_string(),
+            "application/octet-stream".to_string(),
+        );
+
+        let body = |app_state: &AppState, id: u32| match &app_state.request_builders[&id].body {
+            RequestBody::Bytes(bytes) => Arc::clone(bytes),
+            other => panic!("expected a binary body, got {other:?}"),
+        };
+        let shared = body(&app_state, with_body.rep());
+        assert!(Arc::ptr_eq(&shared, &body(&app_state, with_header.rep())));
+        assert_eq!(app_state.request_builders[&with_header.rep()].headers.len(), 2);
+
+        HostRequestBuilder::drop(&mut app_state, with_body).unwrap();
+        HostRequestBuilder::drop(&mut app_state, with_header).unwrap();
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn sends_other_methods_and_encoded_query_parameters() {
+        use crate::transport::Transport;
+        use std::sync::Mutex;
+        use v4_2::standout::app::http::{HostRequestBuilder, Method};
+
+        #[derive(Debug, Default)]
+        struct RecordingTransport(Mutex<Vec<(String, String)>>);
+
+        impl Transport for RecordingTransport {
+            fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+                self.0.lock().unwrap().push((request.method.clone(), request.url.clone()));
+                Ok(HttpResponse { status: 207, ..Default::default() })
+            }
+        }
+
+        let transport = Arc::new(RecordingTransport::default());
+        let mut app_state = AppState::default();
+        app_state.transport = transport.clone();
+
+        let builder = app_state.new();
+        let builder = app_state.method(builder, Method::Other("PROPFIND".to_string()));
+        let builder = app_state.query(builder, "q".to_string(), "a&b c".to_string());
+        let builder = app_state.url(builder, "https://93.184.215.14/cal/?depth=1".to_string());
+        let builder = app_state.queries(builder, vec![("tag".to_string(), "é/ü".to_string())]);
+
+        let object = app_state.object(Resource::new_borrow(builder.rep()));
+        assert!(matches!(&object.method, Method::Other(method) if method == "PROPFIND"));
+        assert_eq!(object.url, "https://93.184.215.14/cal/?depth=1&q=a%26b+c&tag=%C3%A9%2F%C3%BC");
+
+        assert_eq!(app_state.send(builder).expect("Request failed").status, 207);
+        assert_eq!(transport.0.lock().unwrap()[0], ("PROPFIND".to_string(), object.url));
+    }
+
+    #[test]
+    fn sends_form_and_multipart_bodies() {
+        use v4_2::standout::app::file::FileData;
+        use v4_2::standout::app::http::{FilePart, HostRequestBuilder, Method, MultipartPart, TextPart};
+
+        let server = MockServer::start();
+        let form = server.mock(|when, then| {
+            when.method(POST)
+                .path("/form")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body("name=Jane+Doe&tags=a%2Cb");
+            then.status(200);
+        });
+        let multipart = server.mock(|when, then| {
+            when.method(POST)
+                .path("/upload")
+                .header("Content-Type", "multipart/form-data; boundary=app-bridge-boundary-0")
+                .body_includes("filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nhello\r\n");
+            then.status(201);
+        });
+
+        let mut app_state = local_app_state();
+        let builder = app_state.new();
+        let builder = app_state.method(builder, Method::Post);
+        let builder = app_state.url(builder, format!("{}/form", server.base_url()));
+        let builder = app_state.header(builder, "Content-Type".to_string(), "text/plain".to_string());
+        let fields = vec![
+            ("name".to_string(), "Jane Doe".to_string()),
+            ("tags".to_string(), "a,b".to_string()),
+        ];
+        let builder = app_state.form(builder, fields);
+        assert_eq!(app_state.send(builder).expect("Request failed").status, 200);
+
+        let builder = app_state.new();
+        let builder = app_state.method(builder, Method::Post);
+        let builder = app_state.url(builder, format!("{}/upload", server.base_url()));
+        let parts = vec![
+            MultipartPart::Text(TextPart {
+                name: "title".to_string(),
+                value: "Greeting".to_string(),
+            }),
+            MultipartPart::File(FilePart {
+                name: "file".to_string(),
+                file: FileData {
+                    base64: "aGVsbG8=".to_string(),
+                    content_type: "text/plain".to_string(),
+                    filename: "a.txt".to_string(),
+                },
+            }),
+        ];
+        let builder = app_state.multipart(builder, parts);
+        assert_eq!(app_state.send(builder).expect("Request failed").status, 201);
+
+        form.assert();
+        multipart.assert();
+    }
+
+    #[test]
+    fn unchanged_responses_are_served_from_the_cache() {
+        use crate::http_cache::HttpCache;
+        use std::collections::HashMap;
+        use std::sync::Mutex;
+        use v4_2::standout::app::http::HostRequestBuilder;
+
+        #[derive(Debug, Default)]
+        struct MemoryCache(Mutex<HashMap<String, String>>);
+
+        impl HttpCache for MemoryCache {
+            fn read(&self, key: &str) -> Result<Option<String>, String> {
+                Ok(self.0.lock().unwrap().get(key).cloned())
+            }
+
+            fn write(&self, key: &str, value: &str) -> Result<(), String> {
+                self.0.lock().unwrap().insert(key.to_string(), value.to_string());
+                Ok(())
+            }
+        }
+
+        let server = MockServer::start();
+        let not_modified = server.mock(|when, then| {
+            when.method(GET).path("/events").header("If-None-Match", "\"v1\"");
+            then.status(304).header("ETag", "\"v2\"");
+        });
+        let still_not_modified = server.mock(|when, then| {
+            when.method(GET).path("/events").header("If-None-Match", "\"v2\"");
+            then.status(304);
+        });
+        let events = server.mock(|when, then| {
+            when.method(GET).path("/events");
+            then.status(200).header("ETag", "\"v1\"").body("[1,2,3]");
+        });
+
+        let mut app_state = local_app_state();
+        app_state.http.cache = Some(Arc::new(MemoryCache::default()));
+        app_state.connection_id = Some("connection-1".to_string());
+        let mut send = || {
+            let builder = app_state.new();
+            let builder = app_state.url(builder, format!("{}/events", server.base_url()));
+            app_state.send(builder).expect("Request failed")
+        };
+
+        let first = send();
+        let second = send();
+        let third = send();
+
+        assert_eq!((first.status, first.body.as_str()), (200, "[1,2,3]"));
+        assert_eq!((second.status, second.body.as_str()), (200, "[1,2,3]"));
+        assert_eq!((third.status, third.body.as_str()), (200, "[1,2,3]"));
+        assert!(third.headers.iter().any(|(name, value)| name.eq_ignore_ascii_case("etag") && value == "\"v2\""));
+        events.assert_calls(1);
+        not_modified.assert_calls(1);
+        still_not_modified.assert_calls(1);
+        let statuses: Vec<_> = app_state.take_http_log().iter().map(|entry| entry.status).collect();
+        assert_eq!(statuses, [Some(200), Some(304), Some(304)]);
+    }
 }
@@ -0,0 +1,181 @@
+//! Retrying requests that a server rate limited or couldn't serve for a
+//! moment.
+//!
+//! Only idempotent requests are retried, and only when the response is a 429,
+//! 502, 503 or 504. The wait before a retry is what the server's
+//! `Retry-After` asks for, or an exponential backoff without one. A retry
+//! that would start after the invocation's deadline, or that the server asks
+//! to wait longer than `max_backoff` for, isn't made; the last response is
+//! returned instead.
+
+use crate::transport::{HttpRequest, HttpResponse, HttpSender, RequestError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Statuses that say a later attempt may succeed.
+const RETRY_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+/// Methods that are safe to send more than once.
+const IDEMPOTENT_METHODS: [&str; 5] = ["GET", "HEAD", "OPTIONS", "PUT", "DELETE"];
+
+/// How often and how patiently a request is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for every further retry.
+    pub initial_backoff: Duration,
+    /// Longest wait between attempts, whether backing off or waiting as
+    /// long as the server asks.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before retrying `request`, which got `response` on
+    /// its `attempt`th attempt. `None` when it isn't retried.
+    pub fn delay(&self, request: &HttpRequest, response: &HttpResponse, attempt: u32) -> Option<Duration> {
+        let retryable = IDEMPOTENT_METHODS.contains(&request.method.as_str())
+            && RETRY_STATUSES.contains(&response.status)
+            && attempt < self.max_attempts;
+        if !retryable {
+            return None;
+        }
+
+        match retry_after(response) {
+            Some(delay) => (delay <= self.max_backoff).then_some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// The backoff after the `attempt`th attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// The wait a response asks for in `Retry-After`, given in seconds or as an
+/// HTTP date.
+fn retry_after(response: &HttpResponse) -> Option<Duration> {
+    let (_, value) = response
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))?;
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+impl HttpSender<'_> {
+    /// Sends `request`, retrying it as `policy` allows. Every attempt gets
+    /// the `requested` timeout, capped at the time left until the deadline.
+    /// Returns the last response together with the number of attempts.
+    pub fn send_with_retries(
+        &self,
+        mut request: HttpRequest,
+        requested: Option<Duration>,
+        policy: Option<RetryPolicy>,
+    ) -> Result<(HttpResponse, u32), RequestError> {
+        let mut attempt = 1;
+        loop {
+            request.timeout = self.http.timeout_for(requested, self.deadline);
+            let response = self.send(&request)?;
+
+            let Some(delay) = policy.and_then(|policy| policy.delay(&request, &response, attempt)) else {
+                return Ok((response, attempt));
+            };
+            if self.deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+                return Ok((response, attempt));
+            }
+
+            // Replayed responses were recorded with the waits already taken.
+            if !self.http.cassette.as_ref().is_some_and(|cassette| cassette.is_replaying()) {
+                std::thread::sleep(delay);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: "https://example.com/".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            timeout: None,
+            max_response_size: None,
+        }
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let delays: Vec<_> = (1..5)
+            .map(|attempt| policy.delay(&request("GET"), &response(503, &[]), attempt).unwrap())
+            .collect();
+
+        assert_eq!(delays, [100, 200, 400, 500].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let policy = RetryPolicy::default();
+
+        let seconds = response(429, &[("Retry-After", "7")]);
+        assert_eq!(policy.delay(&request("GET"), &seconds, 1), Some(Duration::from_secs(7)));
+
+        let past = response(429, &[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(policy.delay(&request("GET"), &past, 1), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn gives_up_when_retry_after_exceeds_the_maximum_backoff() {
+        let policy = RetryPolicy::default();
+
+        let tomorrow = response(429, &[("Retry-After", "86400")]);
+        assert_eq!(policy.delay(&request("GET"), &tomorrow, 1), None);
+
+        let at_most = response(503, &[("Retry-After", "10")]);
+        assert_eq!(policy.delay(&request("GET"), &at_most, 1), Some(policy.max_backoff));
+    }
+
+    #[test]
+    fn retries_only_idempotent_requests_on_retryable_statuses() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.delay(&request("PUT"), &response(502, &[]), 1).is_some());
+        assert!(policy.delay(&request("POST"), &response(503, &[]), 1).is_none());
+        assert!(policy.delay(&request("GET"), &response(500, &[]), 1).is_none());
+        assert!(policy.delay(&request("GET"), &response(503, &[]), 3).is_none());
+    }
+}
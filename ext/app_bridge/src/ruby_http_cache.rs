@@ -0,0 +1,50 @@
+//! HTTP cache store backed by a Ruby object.
+//!
+//! The object is called as `read(key)`, returning the stored string or
+//! `nil`, and `write(key, value)`, the interface of `Rails.cache` and the
+//! `ActiveSupport::Cache` stores. How long entries live is up to the store.
+
+use magnus::prelude::*;
+use magnus::value::{InnerValue, Opaque};
+use magnus::{Error, Ruby, Value};
+use std::fmt;
+
+use crate::gvl::with_gvl;
+use crate::http_cache::HttpCache;
+
+pub struct RubyHttpCache {
+    store: Opaque<Value>,
+}
+
+impl RubyHttpCache {
+    /// Wraps `store`, which the caller must keep alive and in place for as
+    /// long as the cache is used, see `RubyValues`.
+    pub fn new(store: Value) -> Self {
+        Self { store: store.into() }
+    }
+
+    /// Calls `f` with the store, holding the GVL.
+    fn call<T>(&self, f: impl FnOnce(Value) -> Result<T, Error>) -> Result<T, String> {
+        with_gvl(|| {
+            let ruby = Ruby::get().map_err(|e| e.to_string())?;
+            f(self.store.get_inner_with(&ruby)).map_err(|e| e.to_string())
+        })
+        .unwrap_or_else(|| Err("HTTP cache can only be called from a Ruby thread".to_string()))
+    }
+}
+
+impl fmt::Debug for RubyHttpCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RubyHttpCache")
+    }
+}
+
+impl HttpCache for RubyHttpCache {
+    fn read(&self, key: &str) -> Result<Option<String>, String> {
+        self.call(|store| store.funcall("read", (key,)))
+    }
+
+    fn write(&self, key: &str, value: &str) -> Result<(), String> {
+        self.call(|store| store.funcall::<_, _, Value>("write", (key, value)).map(|_| ()))
+    }
+}
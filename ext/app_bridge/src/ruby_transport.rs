@@ -0,0 +1,164 @@
+//! HTTP transport that hands each request to a Ruby callable.
+//!
+//! The callable is called as `call(method, url, headers, body)` with the
+//! headers as a hash, and returns `[status, headers, body]`, where headers
+//! is a hash or an array of pairs. This lets apps route component traffic
+//! through their own HTTP stack, instrumentation or test stubs.
+//!
+//! `AppBridge::HttpTransport.call` makes the call, so the request's timeout
+//! applies to the callable too. Exceptions the callable raises are mapped to
+//! the `RequestError` variant for their class where there is one, like
+//! `Timeout::Error` or `Errno::ECONNREFUSED`.
+
+use magnus::prelude::*;
+use magnus::value::{InnerValue, Opaque};
+use magnus::{Error, RArray, RClass, RHash, RModule, RString, Ruby, TryConvert, Value};
+use std::fmt;
+use std::time::Duration;
+
+use crate::gvl::with_gvl;
+use crate::transport::{timeout_error, HttpRequest, HttpResponse, RequestError, Transport};
+
+/// Builds a `RequestError` variant from its message.
+type ErrorVariant = fn(String) -> RequestError;
+
+/// Ruby exceptions that stand for a known kind of request failure. Classes
+/// that aren't loaded, like `OpenSSL::SSL::SSLError` without `openssl`, are
+/// skipped.
+const ERROR_CLASSES: &[(&str, ErrorVariant)] = &[
+    ("Timeout::Error", RequestError::Timeout),
+    ("SocketError", RequestError::Dns),
+    ("Errno::ECONNREFUSED", RequestError::Connect),
+    ("Errno::ECONNRESET", RequestError::Connect),
+    ("Errno::EHOSTUNREACH", RequestError::Connect),
+    ("Errno::ENETUNREACH", RequestError::Connect),
+    ("OpenSSL::SSL::SSLError", RequestError::Tls),
+];
+
+pub struct RubyTransport {
+    callable: Opaque<Value>,
+}
+
+impl RubyTransport {
+    /// Wraps `callable`, which the caller must keep alive and in place for
+    /// as long as the transport is used, see `RubyValues`.
+    pub fn new(callable: Value) -> Self {
+        Self {
+            callable: callable.into(),
+        }
+    }
+
+    /// Calls the callable. Must be called holding the GVL.
+    fn call(&self, ruby: &Ruby, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let headers = ruby.hash_new();
+        for (key, value) in merge_headers(&request.headers) {
+            headers.aset(key, value)?;
+        }
+
+        let http_transport: RModule = ruby
+            .class_object()
+            .const_get::<_, RModule>("AppBridge")?
+            .const_get("HttpTransport")?;
+        let result: Value = http_transport.funcall(
+            "call",
+            (
+                self.callable.get_inner_with(ruby),
+                request.timeout.map(|timeout| timeout.as_secs_f64()),
+                request.method.as_str(),
+                request.url.as_str(),
+                headers,
+                ruby.str_from_slice(&request.body),
+            ),
+        )?;
+
+        let (status, headers, body): (u16, Value, Option<RString>) = TryConvert::try_convert(result)?;
+
+        Ok(HttpResponse {
+            status,
+            headers: response_headers(headers)?,
+            // SAFETY: the bytes are copied while the GVL is held, before Ruby
+            // can run again and change or free the string.
+            body: body.map(|body| unsafe { body.as_slice() }.to_vec()).unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Debug for RubyTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RubyTransport")
+    }
+}
+
+impl Transport for RubyTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        // Timeout.timeout treats 0 as no timeout at all.
+        if request.timeout == Some(Duration::ZERO) {
+            return Err(timeout_error(request, Duration::ZERO));
+        }
+
+        let result = with_gvl(|| {
+            let ruby = Ruby::get().map_err(|e| RequestError::Other(e.to_string()))?;
+            self.call(&ruby, request).map_err(|e| request_error(&ruby, request, e))
+        });
+
+        result.unwrap_or_else(|| {
+            Err(RequestError::Other(
+                "HTTP transport can only be called from a Ruby thread".to_string(),
+            ))
+        })
+    }
+
+    /// The callable needs the GVL, which other threads can't take.
+    fn is_concurrent(&self) -> bool {
+        false
+    }
+}
+
+/// Classifies an exception raised by the callable by its class. Must be
+/// called holding the GVL.
+fn request_error(ruby: &Ruby, request: &HttpRequest, error: Error) -> RequestError {
+    let message = format!("HTTP transport failed for {} {}: {}", request.method, request.url, error);
+    let variant = ERROR_CLASSES.iter().find_map(|(name, variant)| {
+        let class: RClass = ruby.class_object().funcall("const_get", (*name,)).ok()?;
+        error.is_kind_of(class).then_some(variant)
+    });
+
+    match variant {
+        Some(variant) => variant(message),
+        None => RequestError::Other(message),
+    }
+}
+
+/// Joins repeated request headers with ", " since a Ruby hash holds one
+/// value per name.
+fn merge_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    let mut merged: Vec<(String, String)> = Vec::new();
+    for (key, value) in headers {
+        match merged.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            Some((_, existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            None => merged.push((key.clone(), value.clone())),
+        }
+    }
+    merged
+}
+
+/// Reads response headers given as a hash or as an array of pairs.
+fn response_headers(headers: Value) -> Result<Vec<(String, String)>, Error> {
+    if headers.is_nil() {
+        return Ok(Vec::new());
+    }
+    if let Ok(array) = RArray::try_convert(headers) {
+        return array.to_vec();
+    }
+
+    let hash = RHash::try_convert(headers)?;
+    let mut pairs = Vec::new();
+    hash.foreach(|key: String, value: String| {
+        pairs.push((key, value));
+        Ok(magnus::r_hash::ForEach::Continue)
+    })?;
+    Ok(pairs)
+}
@@ -0,0 +1,744 @@
+//! Transports that carry the HTTP requests components make.
+//!
+//! Requests from the http interface and downloads from `file.normalize` are
+//! turned into an `HttpRequest` and sent through the store's `Transport`:
+//! the built-in reqwest client, or one the app was configured with. Whatever
+//! the transport, `HttpSender::send` applies the app's egress policy and
+//! cassette first, and logs the request. Transports that resolve host names
+//! some other way have the addresses checked before the request is sent.
+
+use crate::app_state::AppState;
+use crate::cassette::{RecordedBody, RecordedRequest, RecordedResponse};
+use crate::egress::{self, EgressDenied, EgressPolicy, PolicyResolver};
+use crate::http_options::{ClientOptions, HttpOptions};
+use crate::http_log::HttpLogEntry;
+use encoding_rs::{Encoding, UTF_8};
+use std::fmt;
+use std::io::Read;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Redirects the built-in client follows unless configured otherwise.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// A request ready to be sent.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// How long the transport may take, if limited.
+    pub timeout: Option<Duration>,
+    /// Largest response body accepted, in bytes, if limited.
+    pub max_response_size: Option<usize>,
+}
+
+/// The response to an `HttpRequest`.
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// The body as text, decoded with the charset its Content-Type names, or
+    /// as UTF-8 without one. Invalid sequences are replaced with U+FFFD
+    /// rather than failing.
+    pub fn text(&self) -> String {
+        let encoding = self
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .and_then(|(_, value)| charset(value))
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or(UTF_8);
+
+        let (text, _, _) = encoding.decode(&self.body);
+        text.into_owned()
+    }
+}
+
+/// The charset parameter of a Content-Type, e.g. `iso-8859-1` in
+/// `text/plain; charset="iso-8859-1"`.
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Host-side request error, converted to each version's `request-error`.
+#[derive(Debug, Clone)]
+pub enum RequestError {
+    Timeout(String),
+    Connect(String),
+    Dns(String),
+    Tls(String),
+    TooManyRedirects(String),
+    BodyTooLarge(String),
+    Forbidden(String),
+    Other(String),
+}
+
+impl RequestError {
+    /// The same error with its message changed by `f`.
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::Timeout(msg) => Self::Timeout(f(msg)),
+            Self::Connect(msg) => Self::Connect(f(msg)),
+            Self::Dns(msg) => Self::Dns(f(msg)),
+            Self::Tls(msg) => Self::Tls(f(msg)),
+            Self::TooManyRedirects(msg) => Self::TooManyRedirects(f(msg)),
+            Self::BodyTooLarge(msg) => Self::BodyTooLarge(f(msg)),
+            Self::Forbidden(msg) => Self::Forbidden(f(msg)),
+            Self::Other(msg) => Self::Other(f(msg)),
+        }
+    }
+
+    pub fn into_message(self) -> String {
+        match self {
+            Self::Timeout(msg)
+            | Self::Connect(msg)
+            | Self::Dns(msg)
+            | Self::Tls(msg)
+            | Self::TooManyRedirects(msg)
+            | Self::BodyTooLarge(msg)
+            | Self::Forbidden(msg)
+            | Self::Other(msg) => msg,
+        }
+    }
+}
+
+/// Sends requests on behalf of a store.
+pub trait Transport: fmt::Debug + Send + Sync {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError>;
+
+    /// Whether `send` may be called from threads other than the invoking
+    /// one, so batches can be sent concurrently.
+    fn is_concurrent(&self) -> bool {
+        true
+    }
+
+    /// Whether the transport checks the addresses it connects to against
+    /// the egress policy itself. Otherwise hosts are resolved and checked
+    /// before `send`.
+    fn checks_resolved_addresses(&self) -> bool {
+        false
+    }
+}
+
+/// The parts of a store that sending requests needs. Unlike the store it can
+/// be shared between threads.
+#[derive(Debug, Clone, Copy)]
+pub struct HttpSender<'a> {
+    pub http: &'a HttpOptions,
+    pub transport: &'a dyn Transport,
+    /// When the running invocation times out, if it has a deadline.
+    pub deadline: Option<Instant>,
+    log: &'a Mutex<Vec<HttpLogEntry>>,
+}
+
+impl AppState {
+    pub fn http_sender(&self) -> HttpSender<'_> {
+        HttpSender {
+            http: &self.http,
+            transport: self.transport.as_ref(),
+            deadline: self.deadline,
+            log: &self.http_log,
+        }
+    }
+
+    /// Sends `request` with `HttpSender::send`.
+    pub fn send_http(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        self.http_sender().send(request)
+    }
+
+    /// Takes the requests logged since the last call.
+    pub fn take_http_log(&self) -> Vec<HttpLogEntry> {
+        std::mem::take(&mut *self.http_log.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl HttpSender<'_> {
+    /// Sends `request` through the store's transport. The egress policy is
+    /// checked first, and with a cassette the request is replayed from it or
+    /// recorded to it. The request and its outcome are added to the HTTP log,
+    /// and errors are redacted.
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        let started = Instant::now();
+        let redactor = &self.http.redactor;
+        let result = self
+            .send_unlogged(request)
+            .map_err(|error| error.map_message(|message| redactor.message(&message, request)));
+
+        let (status, response_size, error) = match &result {
+            Ok(response) => (Some(response.status), Some(response.body.len()), None),
+            Err(error) => (None, None, Some(error.clone().into_message())),
+        };
+        let entry = HttpLogEntry {
+            method: request.method.clone(),
+            url: redactor.url(&request.url),
+            status,
+            duration: started.elapsed(),
+            request_size: request.body.len(),
+            response_size,
+            error,
+        };
+        self.log.lock().unwrap_or_else(PoisonError::into_inner).push(entry);
+
+        result
+    }
+
+    fn send_unlogged(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        // Unparsable URLs are left for the transport to report.
+        if let Ok(url) = reqwest::Url::parse(&request.url) {
+            if let Err(denied) = self.http.egress.check_url(&url) {
+                return Err(forbidden_error(request, &denied));
+            }
+        }
+
+        let Some(cassette) = &self.http.cassette else {
+            return self.send_through_transport(request);
+        };
+
+        let recorded = RecordedRequest {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            headers: request.headers.clone(),
+            body: RecordedBody::new(&request.body),
+        };
+
+        if cassette.is_replaying() {
+            let response = cassette.replay(&recorded).map_err(RequestError::Other)?;
+            let response = HttpResponse {
+                status: response.status,
+                body: response.body.to_bytes().map_err(RequestError::Other)?,
+                headers: response.headers,
+            };
+            return within_limit(request, response);
+        }
+
+        let response = self.send_through_transport(request)?;
+        let recorded_response = RecordedResponse {
+            status: response.status,
+            headers: response.headers.clone(),
+            body: RecordedBody::new(&response.body),
+        };
+        cassette.record(recorded, recorded_response).map_err(RequestError::Other)?;
+
+        Ok(response)
+    }
+
+    fn send_through_transport(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        if !self.transport.checks_resolved_addresses() {
+            if let Ok(url) = reqwest::Url::parse(&request.url) {
+                self.http
+                    .egress
+                    .check_resolved(&url)
+                    .map_err(|denied| forbidden_error(request, &denied))?;
+            }
+        }
+
+        self.transport.send(request).and_then(|response| within_limit(request, response))
+    }
+}
+
+// ============================================================================
+// Built-in transport
+// ============================================================================
+
+/// Sends requests with reqwest. Host names are resolved through the egress
+/// policy and redirects are checked against it.
+#[derive(Debug)]
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+    /// The egress policy when requests go through a proxy, which resolves
+    /// their hosts itself, so they are resolved and checked before sending.
+    proxied: Option<Arc<EgressPolicy>>,
+}
+
+impl ReqwestTransport {
+    /// Builds the client. Fails on an invalid proxy URL, certificate or key.
+    pub fn new(egress: &Arc<EgressPolicy>, options: &ClientOptions) -> Result<Self, String> {
+        let proxy = options
+            .proxy
+            .as_deref()
+            .map(|url| {
+                let host = reqwest::Url::parse(url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string));
+                let proxy = reqwest::Proxy::all(url).map_err(|e| format!("invalid proxy {}: {}", url, e))?;
+                Ok::<_, String>((proxy, host))
+            })
+            .transpose()?;
+
+        let resolver = PolicyResolver::new(Arc::clone(egress), proxy.as_ref().and_then(|(_, host)| host.clone()));
+        let mut builder = reqwest::blocking::Client::builder()
+            .dns_resolver(Arc::new(resolver))
+            .redirect(egress.redirect_policy(
+                options.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS),
+                proxy.is_some(),
+            ));
+
+        if let Some((proxy, _)) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        for pem in &options.root_certificates {
+            let certificates = reqwest::Certificate::from_pem_bundle(pem)
+                .map_err(|e| format!("invalid root certificate: {}", e))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(identity) = &options.identity {
+            let identity = reqwest::Identity::from_pkcs8_pem(&identity.certificate, &identity.key)
+                .map_err(|e| format!("invalid client certificate or key: {}", e))?;
+            builder = builder.identity(identity);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| format!("failed to build HTTP client: {}", error_chain(&e)))?;
+        Ok(Self {
+            client,
+            proxied: options.proxy.is_some().then(|| Arc::clone(egress)),
+        })
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+        if request.timeout == Some(Duration::ZERO) {
+            return Err(timeout_error(request, Duration::ZERO));
+        }
+
+        if let (Some(egress), Ok(url)) = (&self.proxied, reqwest::Url::parse(&request.url)) {
+            egress
+                .check_resolved(&url)
+                .map_err(|denied| forbidden_error(request, &denied))?;
+        }
+
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|_| RequestError::Other(format!("Invalid HTTP method: {}", request.method)))?;
+        let mut builder = self.client.request(method, &request.url);
+
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        for (key, value) in &request.headers {
+            builder = builder.header(key, value);
+        }
+
+        let response = builder
+            .body(request.body.clone())
+            .send()
+            .map_err(|error| request_error(request, error))?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or_default().to_string()))
+            .collect();
+        let body = read_body(request, response)?;
+
+        Ok(HttpResponse { status, headers, body })
+    }
+
+    /// Hosts are resolved through `PolicyResolver`, or checked in `send`
+    /// when a proxy resolves them.
+    fn checks_resolved_addresses(&self) -> bool {
+        true
+    }
+}
+
+/// Reads the body of `response`, giving up as soon as it is larger than the
+/// request allows.
+fn read_body(request: &HttpRequest, response: reqwest::blocking::Response) -> Result<Vec<u8>, RequestError> {
+    let Some(limit) = request.max_response_size else {
+        return response
+            .bytes()
+            .map(|body| body.to_vec())
+            .map_err(|error| request_error(request, error));
+    };
+
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(body_too_large_error(request, limit));
+    }
+
+    let mut body = Vec::new();
+    response
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|error| read_error(request, error))?;
+
+    if body.len() > limit {
+        return Err(body_too_large_error(request, limit));
+    }
+    Ok(body)
+}
+
+/// Classifies an error reading a body, which wraps the reqwest error.
+fn read_error(request: &HttpRequest, error: std::io::Error) -> RequestError {
+    if error.kind() == std::io::ErrorKind::TimedOut {
+        return timeout_error(request, request.timeout.unwrap_or_default());
+    }
+
+    let message = error.to_string();
+    match error.into_inner().and_then(|inner| inner.downcast::<reqwest::Error>().ok()) {
+        Some(error) => request_error(request, *error),
+        None => RequestError::Other(format!("Request failed to {} {}: {}", request.method, request.url, message)),
+    }
+}
+
+/// Checks the body of a response from a transport that doesn't enforce the
+/// size limit itself.
+fn within_limit(request: &HttpRequest, response: HttpResponse) -> Result<HttpResponse, RequestError> {
+    match request.max_response_size {
+        Some(limit) if response.body.len() > limit => Err(body_too_large_error(request, limit)),
+        _ => Ok(response),
+    }
+}
+
+/// Classifies a reqwest error by the kind of failure.
+///
+/// reqwest only flags timeouts, redirects and connect errors, so DNS and TLS
+/// failures are told apart by the errors they were caused by.
+fn request_error(request: &HttpRequest, error: reqwest::Error) -> RequestError {
+    if let Some(denied) = egress::denied_cause(&error) {
+        return forbidden_error(request, denied);
+    }
+    if error.is_timeout() {
+        return timeout_error(request, request.timeout.unwrap_or_default());
+    }
+
+    let message = format!("Request failed to {} {}: {}", request.method, request.url, error_chain(&error));
+
+    if error.is_redirect() {
+        RequestError::TooManyRedirects(message)
+    } else if error.is_connect() {
+        let causes = error_chain(&error).to_lowercase();
+        if causes.contains("dns error") {
+            RequestError::Dns(message)
+        } else if ["tls", "ssl", "certificate", "handshake"].iter().any(|s| causes.contains(s)) {
+            RequestError::Tls(message)
+        } else {
+            RequestError::Connect(message)
+        }
+    } else {
+        RequestError::Other(message)
+    }
+}
+
+/// The error and its causes, e.g. `error sending request: dns error: ...`.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause = cause.to_string();
+        if !message.contains(&cause) {
+            message = format!("{message}: {cause}");
+        }
+        source = source.and_then(std::error::Error::source);
+    }
+    message
+}
+
+fn forbidden_error(request: &HttpRequest, denied: &EgressDenied) -> RequestError {
+    RequestError::Forbidden(format!("Request to {} {} {}", request.method, request.url, denied))
+}
+
+fn body_too_large_error(request: &HttpRequest, limit: usize) -> RequestError {
+    RequestError::BodyTooLarge(format!(
+        "Response to {} {} is larger than {} bytes",
+        request.method, request.url, limit
+    ))
+}
+
+pub fn timeout_error(request: &HttpRequest, timeout: Duration) -> RequestError {
+    RequestError::Timeout(format!(
+        "Request to {} {} timed out after {} ms",
+        request.method,
+        request.url,
+        timeout.as_millis()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_options::HttpOptions;
+    use std::sync::Mutex;
+    use wasmtime_wasi::WasiCtxBuilder;
+
+    /// Answers every request with 204 and remembers the URLs it was sent.
+    #[derive(Debug, Default)]
+    struct StubTransport {
+        urls: Mutex<Vec<String>>,
+    }
+
+    impl Transport for StubTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+            self.urls.lock().unwrap().push(request.url.clone());
+            Ok(HttpResponse {
+                status: 204,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            timeout: None,
+            max_response_size: None,
+        }
+    }
+
+    #[test]
+    fn custom_transport_is_subject_to_the_egress_policy() {
+        let transport = Arc::new(StubTransport::default());
+        let http = HttpOptions {
+            transport: Some(transport.clone()),
+            ..Default::default()
+        };
+        let state = AppState::new(WasiCtxBuilder::new().build(), None, http);
+
+        assert_eq!(state.send_http(&get("https://93.184.215.14/")).unwrap().status, 204);
+        assert!(matches!(
+            state.send_http(&get("http://169.254.169.254/")),
+            Err(RequestError::Forbidden(_))
+        ));
+        // The transport doesn't resolve through the policy, so names that
+        // resolve to internal addresses are refused too.
+        assert!(matches!(
+            state.send_http(&get("http://localhost/admin")),
+            Err(RequestError::Forbidden(message)) if message.contains("localhost resolves to internal address")
+        ));
+        assert_eq!(*transport.urls.lock().unwrap(), vec!["https://93.184.215.14/".to_string()]);
+    }
+
+    #[test]
+    fn requests_are_logged_with_redacted_urls() {
+        let http = HttpOptions {
+            transport: Some(Arc::new(StubTransport::default())),
+            ..Default::default()
+        };
+        let state = AppState::new(WasiCtxBuilder::new().build(), None, http);
+
+        let mut post = get("https://93.184.215.14/items?api_key=s3cret");
+        post.method = "POST".to_string();
+        post.body = b"{}".to_vec();
+        state.send_http(&post).unwrap();
+        let _ = state.send_http(&get("http://10.0.0.1/?token=s3cret"));
+
+        let log = state.take_http_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].url, "https://93.184.215.14/items?api_key=REDACTED");
+        assert_eq!((log[0].status, log[0].request_size, log[0].response_size), (Some(204), 2, Some(0)));
+        assert_eq!(log[1].status, None);
+        let error = log[1].error.as_deref().unwrap();
+        assert!(error.contains("egress policy") && !error.contains("s3cret"), "{error}");
+        assert!(state.take_http_log().is_empty());
+    }
+
+    #[test]
+    fn request_errors_are_redacted() {
+        /// Fails like a transport that echoes the request in its error.
+        #[derive(Debug)]
+        struct EchoingTransport;
+
+        impl Transport for EchoingTransport {
+            fn send(&self, request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+                Err(RequestError::Connect(format!(
+                    "Request failed to {} {}: rejected {:?}",
+                    request.method, request.url, request.headers
+                )))
+            }
+        }
+
+        let http = HttpOptions {
+            transport: Some(Arc::new(EchoingTransport)),
+            ..Default::default()
+        };
+        let state = AppState::new(WasiCtxBuilder::new().build(), None, http);
+        let mut request = get("https://93.184.215.14/?access_token=s3cret-token");
+        request.headers.push(("Authorization".to_string(), "Bearer b34rer-token".to_string()));
+
+        let Err(RequestError::Connect(message)) = state.send_http(&request) else {
+            panic!("expected a connect error");
+        };
+        assert!(message.contains("https://93.184.215.14/?access_token=REDACTED"), "{message}");
+        assert!(!message.contains("s3cret") && !message.contains("b34rer"), "{message}");
+        assert_eq!(state.take_http_log()[0].error.as_deref(), Some(message.as_str()));
+    }
+
+    #[test]
+    fn invalid_client_options_fail_to_build() {
+        let egress = Arc::new(EgressPolicy::default());
+
+        let proxy = ClientOptions {
+            proxy: Some("not a proxy".to_string()),
+            ..Default::default()
+        };
+        assert!(ReqwestTransport::new(&egress, &proxy).unwrap_err().contains("invalid proxy"));
+
+        let certificate = ClientOptions {
+            root_certificates: vec![b"-----BEGIN CERTIFICATE-----\ngarbage\n-----END CERTIFICATE-----\n".to_vec()],
+            ..Default::default()
+        };
+        assert!(ReqwestTransport::new(&egress, &certificate).is_err());
+    }
+
+    #[test]
+    fn redirects_are_limited() {
+        use httpmock::{Method::GET, MockServer};
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/a");
+            then.status(302).header("Location", "/b");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/b");
+            then.status(302).header("Location", "/c");
+        });
+        let egress = Arc::new(EgressPolicy {
+            allow_private_networks: true,
+            ..Default::default()
+        });
+        let send = |max_redirects| {
+            let options = ClientOptions {
+                max_redirects: Some(max_redirects),
+                ..Default::default()
+            };
+            ReqwestTransport::new(&egress, &options).unwrap().send(&get(&server.url("/a")))
+        };
+
+        assert_eq!(send(0).unwrap().status, 302);
+        assert!(matches!(send(1), Err(RequestError::TooManyRedirects(_))));
+    }
+
+    #[test]
+    fn requests_go_through_the_proxy() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let proxy = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut request_line = String::new();
+            BufReader::new(&stream).read_line(&mut request_line).unwrap();
+            (&stream)
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            request_line
+        });
+
+        // The default policy blocks loopback addresses, but not the proxy's.
+        let options = ClientOptions {
+            proxy: Some(format!("http://localhost:{port}")),
+            ..Default::default()
+        };
+        let transport = ReqwestTransport::new(&Arc::new(EgressPolicy::default()), &options).unwrap();
+
+        assert_eq!(transport.send(&get("http://93.184.215.14/items")).unwrap().status, 200);
+        assert!(proxy.join().unwrap().starts_with("GET http://93.184.215.14/items"));
+    }
+
+    #[test]
+    fn hosts_sent_through_the_proxy_are_still_resolved_and_checked() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let options = ClientOptions {
+            proxy: Some(format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port())),
+            ..Default::default()
+        };
+        let transport = ReqwestTransport::new(&Arc::new(EgressPolicy::default()), &options).unwrap();
+
+        let error = transport.send(&get("http://localhost/admin")).unwrap_err();
+        assert!(matches!(error, RequestError::Forbidden(_)), "{error:?}");
+        assert!(error.into_message().contains("localhost resolves to internal address"));
+
+        let allowed = EgressPolicy {
+            allow_private_networks: true,
+            ..Default::default()
+        };
+        assert!(allowed.check_resolved(&reqwest::Url::parse("http://localhost/admin").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn decodes_text_with_the_declared_charset() {
+        let response = |content_type: &str, body: &[u8]| HttpResponse {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.to_vec(),
+        };
+
+        assert_eq!(response("text/plain; charset=\"ISO-8859-1\"", b"caf\xe9").text(), "café");
+        assert_eq!(response("text/plain", b"caf\xc3\xa9").text(), "café");
+        assert_eq!(response("application/json", b"caf\xe9").text(), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn responses_larger_than_the_limit_fail() {
+        use std::io::Write;
+
+        // No Content-Length, so the limit is only noticed while reading.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/large", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = std::io::Read::read(&mut stream, &mut request);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n");
+            let _ = stream.write_all(&[b'x'; 4096]);
+        });
+
+        let egress = Arc::new(EgressPolicy {
+            allow_private_networks: true,
+            ..Default::default()
+        });
+        let transport = ReqwestTransport::new(&egress, &ClientOptions::default()).unwrap();
+        let mut request = get(&url);
+        request.max_response_size = Some(1024);
+
+        let error = transport.send(&request).unwrap_err();
+        assert!(matches!(&error, RequestError::BodyTooLarge(msg) if msg.contains("larger than 1024 bytes")), "{error:?}");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn custom_transport_responses_are_limited() {
+        #[derive(Debug)]
+        struct LargeTransport;
+
+        impl Transport for LargeTransport {
+            fn send(&self, _request: &HttpRequest) -> Result<HttpResponse, RequestError> {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: vec![0; 100],
+                })
+            }
+        }
+
+        let http = HttpOptions {
+            transport: Some(Arc::new(LargeTransport)),
+            ..Default::default()
+        };
+        let state = AppState::new(WasiCtxBuilder::new().build(), None, http);
+        let mut request = get("https://93.184.215.14/");
+
+        request.max_response_size = Some(100);
+        assert!(state.send_http(&request).is_ok());
+        request.max_response_size = Some(99);
+        assert!(matches!(state.send_http(&request), Err(RequestError::BodyTooLarge(_))));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! These are version-agnostic and converted to/from versioned WIT types at the boundary.
 
+use crate::http_log::HttpLogEntry;
 use std::fmt;
 
 // ============================================================================
@@ -46,6 +47,10 @@ pub enum ErrorCode {
     RetryWithReference(ReferenceObject),
     CompleteWorkflow,
     CompleteParent,
+    /// Host-only: the invocation used up its fuel budget.
+    FuelExhausted,
+    /// Host-only: the component exceeded a memory or table limit.
+    ResourceLimit,
 }
 
 #[derive(Debug, Clone)]
@@ -87,9 +92,17 @@ pub struct TriggerEvent {
 pub struct TriggerResponse {
     pub store: String,
     pub events: Vec<TriggerEvent>,
+    /// Fuel used by the invocation, when fuel metering is enabled.
+    pub fuel_consumed: Option<u64>,
+    /// HTTP requests made by the invocation.
+    pub http_log: Vec<HttpLogEntry>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ActionResponse {
     pub serialized_output: String,
+    /// Fuel used by the invocation, when fuel metering is enabled.
+    pub fuel_consumed: Option<u64>,
+    /// HTTP requests made by the invocation.
+    pub http_log: Vec<HttpLogEntry>,
 }
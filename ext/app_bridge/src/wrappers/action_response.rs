@@ -1,4 +1,6 @@
+use magnus::{Error, RArray, Ruby};
 use crate::types::ActionResponse;
+use super::http_log::http_log_array;
 
 #[magnus::wrap(class = "AppBridge::ActionResponse")]
 pub struct RActionResponse {
@@ -9,6 +11,8 @@ impl RActionResponse {
     pub fn new(serialized_output: String) -> Self {
         let inner = ActionResponse {
             serialized_output,
+            fuel_consumed: None,
+            http_log: Vec::new(),
         };
         Self { inner }
     }
@@ -17,9 +21,23 @@ impl RActionResponse {
         self.inner.serialized_output.clone()
     }
 
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        self.inner.fuel_consumed
+    }
+
+    /// The HTTP requests the invocation made, oldest first.
+    pub fn http_log(&self) -> Result<RArray, Error> {
+        let ruby = Ruby::get().unwrap();
+        http_log_array(&ruby, &self.inner.http_log)
+    }
+
     /// Returns a new ActionResponse with the given output
     pub fn with_output(&self, value: String) -> Self {
-        Self::new(value)
+        let inner = ActionResponse {
+            serialized_output: value,
+            ..self.inner.clone()
+        };
+        Self { inner }
     }
 }
 
@@ -1,37 +1,268 @@
-use magnus::{Error, TryConvert, Value};
-use std::cell::RefCell;
+use magnus::{gc, DataTypeFunctions, Error, TryConvert, TypedData, Value};
 use std::collections::HashMap;
-use wasmtime::Store;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use wasmtime::{Store, Trap};
 
 use crate::app_state::AppState;
-use crate::component::{app, build_engine, build_linker, build_store, BridgeWrapper};
+use crate::artifact_cache;
+use crate::component::{
+    build_store, fuel_consumed, set_deadline, set_fuel_budget, BridgePreWrapper, BridgeWrapper,
+};
+use crate::error_mapping::invocation_error;
+use crate::gvl::without_gvl;
+use crate::http_log::HttpLogEntry;
+use crate::http_options::HttpOptions;
+use crate::limits::{ResourceLimitExceeded, ResourceLimits};
+use crate::pool::{InstancePool, Pooled};
+use crate::registry::{self, Runtime};
 use crate::types::{ActionContext, ActionResponse, AppError, ErrorCode, TriggerContext, TriggerResponse};
 use super::{
     action_context::RActionContext,
     action_response::RActionResponse,
+    app_options::{AppOptions, Isolation, RubyValues},
     trigger_context::RTriggerContext,
     trigger_response::RTriggerResponse,
 };
 
+/// A component instance together with the store it lives in.
+type Instance = (BridgeWrapper, Store<AppState>);
+
+/// How long schema calls such as `trigger_ids`, which have no
+/// `timeout_seconds`, wait for a pooled instance.
+const SCHEMA_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Default)]
 pub struct RApp {
-    component_path: String,
-    instance: RefCell<Option<BridgeWrapper>>,
-    store: RefCell<Option<Store<AppState>>>,
+    runtime: Option<&'static Runtime>,
+    component: Option<Arc<BridgePreWrapper>>,
+    wit_version: Option<&'static str>,
+    env_vars: Option<HashMap<String, String>>,
+    fuel: Option<u64>,
+    limits: ResourceLimits,
+    http: HttpOptions,
+    isolation: Isolation,
+    /// Long-lived instances used by schema calls and by invocations with
+    /// `Isolation::Shared`.
+    pool: InstancePool<Instance>,
 }
 
-#[derive(Default)]
-#[magnus::wrap(class = "AppBridge::App")]
-pub struct MutRApp(RefCell<RApp>);
+/// Whether a call failed with a trap rather than an error from the component.
+fn trapped<T>(result: &wasmtime::Result<T>) -> bool {
+    matches!(result, Err(err) if err.downcast_ref::<AppError>().is_none())
+}
+
+/// A successful `fetch_events` or `execute_action` invocation.
+struct Invocation<T> {
+    response: T,
+    fuel_consumed: Option<u64>,
+    http_log: Vec<HttpLogEntry>,
+}
+
+/// A failed invocation, with the HTTP requests it made before failing.
+struct InvocationError {
+    error: AppError,
+    http_log: Vec<HttpLogEntry>,
+}
+
+impl From<AppError> for InvocationError {
+    fn from(error: AppError) -> Self {
+        Self {
+            error,
+            http_log: Vec::new(),
+        }
+    }
+}
+
+fn not_initialized() -> AppError {
+    AppError {
+        code: ErrorCode::InternalError,
+        message: "App instance couldn't be initialized".to_string(),
+    }
+}
+
+/// The error for a call or instantiation that failed on one of `limits`.
+fn resource_limit_error(err: &wasmtime::Error, limits: &ResourceLimits) -> Option<AppError> {
+    ResourceLimitExceeded::find(err, limits).map(|limit| AppError {
+        code: ErrorCode::ResourceLimit,
+        message: limit.to_string(),
+    })
+}
+
+impl RApp {
+    /// Creates a new instance of the component in a new store.
+    fn new_instance(&self) -> Result<Instance, AppError> {
+        let (Some(runtime), Some(component)) = (self.runtime, &self.component) else {
+            return Err(not_initialized());
+        };
+
+        let mut store = build_store(&runtime.engine, self.env_vars.clone(), self.limits, self.http.clone());
+        let instance = component.instantiate(&mut store).map_err(|e| {
+            resource_limit_error(&e, &self.limits).unwrap_or_else(|| AppError {
+                code: ErrorCode::InternalError,
+                message: format!("Failed to instantiate app: {}", e),
+            })
+        })?;
+
+        Ok((instance, store))
+    }
+
+    /// Checks out an instance from the pool. Only call this without holding
+    /// the GVL, since it may wait for another thread to check one in. Fails
+    /// with a timeout when no instance is free before `deadline`.
+    fn checkout(
+        &self,
+        operation: &str,
+        deadline: Option<Instant>,
+        timeout_seconds: f64,
+    ) -> Result<Pooled<Instance>, AppError> {
+        self.pool.checkout(deadline, || self.new_instance())?.ok_or_else(|| AppError {
+            code: ErrorCode::Timeout,
+            message: format!("{} exceeded {} seconds waiting for an instance", operation, timeout_seconds),
+        })
+    }
+
+    /// Returns an instance to the pool. An instance that trapped can't be
+    /// entered again, so it is recycled along with worn out ones.
+    fn checkin(&self, pooled: Pooled<Instance>, trapped: bool) {
+        let memory_size = pooled.instance.1.data().limiter.memory_size();
+        self.pool.checkin(pooled, trapped, memory_size);
+    }
+
+    /// Runs a `fetch_events` or `execute_action` invocation with the deadline
+    /// and fuel budget applied. Returns the result together with the fuel it
+    /// consumed and the HTTP requests it made.
+    ///
+    /// With `Isolation::Invocation` the call runs in a fresh instance and
+    /// store that are dropped afterwards, so no state survives between calls.
+    fn invoke<T>(
+        &self,
+        operation: &str,
+        connection_id: &str,
+        timeout_seconds: f64,
+        call: impl FnOnce(&BridgeWrapper, &mut Store<AppState>) -> wasmtime::Result<Result<T, AppError>>,
+    ) -> Result<Invocation<T>, InvocationError> {
+        // Starts before an instance is checked out or created, so waiting for
+        // one counts against the timeout too.
+        let deadline = Duration::try_from_secs_f64(timeout_seconds)
+            .ok()
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+
+        let (result, fuel_consumed, http_log) = match self.isolation {
+            Isolation::Invocation => {
+                let (instance, mut store) = self.new_instance()?;
+                self.run(&instance, &mut store, connection_id, deadline, call)
+            }
+            Isolation::Shared => {
+                let mut pooled = self.checkout(operation, deadline, timeout_seconds)?;
+                let (instance, store) = &mut pooled.instance;
+                let run = self.run(instance, store, connection_id, deadline, call);
+
+                self.checkin(pooled, trapped(&run.0));
+                run
+            }
+        };
+
+        let error = match result {
+            Ok(Ok(response)) => {
+                return Ok(Invocation {
+                    response,
+                    fuel_consumed,
+                    http_log,
+                })
+            }
+            Ok(Err(err)) => err,
+            Err(err) => self.invocation_error(err, operation, timeout_seconds),
+        };
+        Err(InvocationError { error, http_log })
+    }
+
+    /// Calls into `instance` with the deadline and fuel budget applied, and
+    /// restores the store afterwards. Returns the HTTP requests made during
+    /// the call.
+    fn run<T>(
+        &self,
+        instance: &BridgeWrapper,
+        store: &mut Store<AppState>,
+        connection_id: &str,
+        deadline: Option<Instant>,
+        call: impl FnOnce(&BridgeWrapper, &mut Store<AppState>) -> wasmtime::Result<Result<T, AppError>>,
+    ) -> (wasmtime::Result<Result<T, AppError>>, Option<u64>, Vec<HttpLogEntry>) {
+        set_deadline(store, deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())));
+        set_fuel_budget(store, self.fuel);
+        store.data_mut().connection_id = Some(connection_id.to_string());
+        // Drop requests made outside an invocation, e.g. by schema calls.
+        store.data().take_http_log();
+        let result = call(instance, store);
+        let fuel = fuel_consumed(store, self.fuel);
+        let http_log = store.data().take_http_log();
+        set_deadline(store, None);
+        set_fuel_budget(store, None);
+        store.data_mut().connection_id = None;
+
+        (result, fuel, http_log)
+    }
+
+    /// Converts an error from an invocation into an `AppError`. Traps from
+    /// the deadline, fuel budget and resource limits get their own error codes.
+    fn invocation_error(&self, err: wasmtime::Error, operation: &str, timeout_seconds: f64) -> AppError {
+        if let Some(wit_err) = err.downcast_ref::<AppError>() {
+            return wit_err.clone();
+        }
+
+        if let Some(limit_error) = resource_limit_error(&err, &self.limits) {
+            return limit_error;
+        }
+
+        match err.downcast_ref::<Trap>() {
+            Some(Trap::Interrupt) => AppError {
+                code: ErrorCode::Timeout,
+                message: format!("{} exceeded {} seconds", operation, timeout_seconds),
+            },
+            Some(Trap::OutOfFuel) => AppError {
+                code: ErrorCode::FuelExhausted,
+                message: format!("{} exceeded its fuel budget of {}", operation, self.fuel.unwrap_or_default()),
+            },
+            _ => AppError {
+                code: ErrorCode::InternalError,
+                message: format!("Unexpected error: {:?}", err),
+            },
+        }
+    }
+}
+
+/// `AppBridge::App`. Safe to share between threads: `initialize` replaces
+/// the whole configuration at once, calls work on the configuration they
+/// started with, and calls waiting for a pooled instance do so without
+/// holding the GVL.
+#[derive(Default, TypedData)]
+#[magnus(class = "AppBridge::App", mark)]
+pub struct MutRApp {
+    app: RwLock<Arc<RApp>>,
+    /// The Ruby objects of every configuration the app was initialized
+    /// with, since calls may still be running on an earlier one.
+    ruby_values: Mutex<RubyValues>,
+}
+
+impl DataTypeFunctions for MutRApp {
+    fn mark(&self, marker: &gc::Marker) {
+        self.ruby_values.lock().unwrap_or_else(PoisonError::into_inner).mark(marker);
+    }
+}
 
 impl MutRApp {
+    /// The app as currently initialized. The lock is only held while the
+    /// `Arc` is cloned, so a call can reacquire the GVL while `initialize`
+    /// holds the GVL and waits for the lock.
+    fn snapshot(&self) -> Arc<RApp> {
+        Arc::clone(&self.app.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
     /// Returns the WIT version this component was built against (e.g., "3.0.0", "4.0.0")
     pub fn wit_version(&self) -> Result<String, Error> {
-        let binding = self.0.borrow();
-        let instance = binding.instance.borrow();
-
-        if let Some(instance) = &*instance {
-            Ok(instance.wit_version().to_string())
+        if let Some(version) = self.snapshot().wit_version {
+            Ok(version.to_string())
         } else {
             Err(Error::new(
                 magnus::exception::runtime_error(),
@@ -40,22 +271,42 @@ impl MutRApp {
         }
     }
 
-    pub fn initialize(&self, component_path: String, env_vars: HashMap<String, String>) -> Result<(), Error> {
-        let mut this = self.0.borrow_mut();
-        let engine = build_engine();
-        let linker = build_linker(&engine).map_err(|e| {
+    /// Compiles the component and stores the artifact in `cache_dir`, so that
+    /// apps created with the same `cache_dir` skip compilation.
+    /// Returns the path of the precompiled artifact.
+    pub fn precompile(component_path: String, cache_dir: String, options: AppOptions) -> Result<String, Error> {
+        let runtime = registry::runtime(options.engine_options()).map_err(|e| {
             Error::new(
                 magnus::exception::runtime_error(),
                 format!("Failed to build linker: {}", e),
             )
         })?;
-        let mut store = if env_vars.is_empty() {
-            build_store(&engine, None)
-        } else {
-            build_store(&engine, Some(env_vars))
-        };
+        let path = artifact_cache::precompile(&runtime.engine, &component_path, Path::new(&cache_dir)).map_err(|e| {
+            Error::new(
+                magnus::exception::runtime_error(),
+                format!("Failed to precompile app: {}", e),
+            )
+        })?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
 
-        let app = app(component_path.clone(), engine, &mut store, linker).map_err(|e| {
+    pub fn initialize(
+        &self,
+        component_path: String,
+        env_vars: HashMap<String, String>,
+        options: AppOptions,
+    ) -> Result<(), Error> {
+        let runtime = registry::runtime(options.engine_options()).map_err(|e| {
+            Error::new(
+                magnus::exception::runtime_error(),
+                format!("Failed to build linker: {}", e),
+            )
+        })?;
+        let env_vars = if env_vars.is_empty() { None } else { Some(env_vars) };
+        let mut store = build_store(&runtime.engine, env_vars.clone(), options.limits, options.http.clone());
+
+        let init_error = |e: wasmtime::Error| {
             if e.to_string().contains("Incompatible WASM file version") {
                 Error::new(magnus::exception::runtime_error(), e.to_string())
             } else {
@@ -64,258 +315,144 @@ impl MutRApp {
                     format!("Failed to initialize app: {}", e),
                 )
             }
+        };
+        let component =
+            registry::component(runtime, &component_path, options.cache_dir.as_deref()).map_err(init_error)?;
+        let app = component.instantiate(&mut store).map_err(|e| match resource_limit_error(&e, &options.limits) {
+            Some(limit_error) => limit_error.into(),
+            None => init_error(e),
         })?;
 
-        this.component_path = component_path;
-        *this.instance.borrow_mut() = Some(app);
-        *this.store.borrow_mut() = Some(store);
+        let mut this = RApp {
+            runtime: Some(runtime),
+            wit_version: Some(app.wit_version()),
+            component: Some(component),
+            env_vars,
+            fuel: options.fuel,
+            limits: options.limits,
+            http: options.http,
+            isolation: options.isolation,
+            pool: InstancePool::default(),
+        };
 
+        // Warm the rest of the pool so the first calls don't instantiate.
+        let mut instances = vec![(app, store)];
+        while instances.len() < options.pool.size {
+            instances.push(this.new_instance()?);
+        }
+        this.pool = InstancePool::new(options.pool, instances);
+
+        self.ruby_values
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(&options.ruby_values);
+        *self.app.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(this);
         Ok(())
     }
 
-    pub fn trigger_ids(&self) -> Result<Vec<String>, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            match instance.call_trigger_ids(store) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
+    /// Calls into a pooled instance without holding the GVL.
+    fn call_shared<T>(
+        &self,
+        call: impl FnOnce(&BridgeWrapper, &mut Store<AppState>) -> wasmtime::Result<Result<T, AppError>>,
+    ) -> Result<T, Error> {
+        let result = without_gvl(|| {
+            let app = self.snapshot();
+            let deadline = Instant::now() + SCHEMA_CALL_TIMEOUT;
+            let mut pooled = app.checkout("Schema call", Some(deadline), SCHEMA_CALL_TIMEOUT.as_secs_f64())?;
+            let (instance, store) = &mut pooled.instance;
+            let result = call(instance, store);
+
+            app.checkin(pooled, trapped(&result));
+            Ok::<_, AppError>(result)
+        });
+
+        match result {
+            Ok(Ok(result)) => result.map_err(Into::into),
+            Ok(Err(err)) => {
+                if let Some(wit_err) = err.downcast_ref::<AppError>() {
+                    Err(wit_err.clone().into())
+                } else {
+                    Err(Error::new(
+                        magnus::exception::runtime_error(),
+                        format!("Unexpected error: {:?}", err),
+                    ))
                 }
             }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
+            Err(err) => Err(err.into()),
         }
     }
 
+    pub fn trigger_ids(&self) -> Result<Vec<String>, Error> {
+        self.call_shared(|instance, store| instance.call_trigger_ids(store))
+    }
+
     pub fn trigger_input_schema(&self, context: RTriggerContext) -> Result<String, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            let context_ctx = context.into();
-            match instance.call_trigger_input_schema(store, &context_ctx) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
-        }
+        let context_ctx = context.into();
+        self.call_shared(|instance, store| instance.call_trigger_input_schema(store, &context_ctx))
     }
 
     pub fn trigger_output_schema(&self, context: RTriggerContext) -> Result<String, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            let context_ctx = context.into();
-            match instance.call_trigger_output_schema(store, &context_ctx) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
-        }
+        let context_ctx = context.into();
+        self.call_shared(|instance, store| instance.call_trigger_output_schema(store, &context_ctx))
     }
 
-    pub fn rb_fetch_events(&self, context: Value) -> Result<RTriggerResponse, magnus::Error> {
+    pub fn rb_fetch_events(&self, context: Value, timeout_seconds: f64) -> Result<RTriggerResponse, magnus::Error> {
         let context: RTriggerContext = TryConvert::try_convert(context).unwrap();
-        let response = self.fetch_events(context.into());
+        let context = context.into();
+        let response = without_gvl(|| self.fetch_events(context, timeout_seconds));
 
         match response {
             Ok(response) => Ok(response.into()),
-            Err(err) => Err(err.into()),
+            Err(err) => Err(invocation_error(err.error, &err.http_log)),
         }
     }
 
-    fn fetch_events(&self, context: TriggerContext) -> Result<TriggerResponse, AppError> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            match instance.call_fetch_events(store, &context) {
-                Ok(response) => response,
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone())
-                    } else {
-                        Err(AppError {
-                            code: ErrorCode::InternalError,
-                            message: format!("Unexpected error: {:?}", err),
-                        })
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            })
-        }
+    fn fetch_events(&self, context: TriggerContext, timeout_seconds: f64) -> Result<TriggerResponse, InvocationError> {
+        let invocation = self.snapshot().invoke("Polling", &context.connection.id, timeout_seconds, |instance, store| {
+            instance.call_fetch_events(store, &context)
+        })?;
+
+        Ok(TriggerResponse {
+            fuel_consumed: invocation.fuel_consumed,
+            http_log: invocation.http_log,
+            ..invocation.response
+        })
     }
 
     pub fn action_ids(&self) -> Result<Vec<String>, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            match instance.call_action_ids(store) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
-        }
+        self.call_shared(|instance, store| instance.call_action_ids(store))
     }
 
     pub fn action_input_schema(&self, context: RActionContext) -> Result<String, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            let context_ctx = context.into();
-            match instance.call_action_input_schema(store, &context_ctx) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
-        }
+        let context_ctx = context.into();
+        self.call_shared(|instance, store| instance.call_action_input_schema(store, &context_ctx))
     }
 
     pub fn action_output_schema(&self, context: RActionContext) -> Result<String, Error> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            let context_ctx = context.into();
-            match instance.call_action_output_schema(store, &context_ctx) {
-                Ok(result) => result.map_err(Into::into),
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone().into())
-                    } else {
-                        Err(Error::new(
-                            magnus::exception::runtime_error(),
-                            format!("Unexpected error: {:?}", err),
-                        ))
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            }
-            .into())
-        }
+        let context_ctx = context.into();
+        self.call_shared(|instance, store| instance.call_action_output_schema(store, &context_ctx))
     }
 
-    pub fn rb_execute_action(&self, context: Value) -> Result<RActionResponse, magnus::Error> {
+    pub fn rb_execute_action(&self, context: Value, timeout_seconds: f64) -> Result<RActionResponse, magnus::Error> {
         let context: RActionContext = TryConvert::try_convert(context).unwrap();
-        let response = self.execute_action(context.into());
+        let context = context.into();
+        let response = without_gvl(|| self.execute_action(context, timeout_seconds));
 
         match response {
             Ok(response) => Ok(response.into()),
-            Err(err) => Err(err.into()),
+            Err(err) => Err(invocation_error(err.error, &err.http_log)),
         }
     }
 
-    fn execute_action(&self, context: ActionContext) -> Result<ActionResponse, AppError> {
-        let binding = self.0.borrow();
-        let mut instance = binding.instance.borrow_mut();
-        let mut store = binding.store.borrow_mut();
-
-        if let (Some(instance), Some(store)) = (&mut *instance, &mut *store) {
-            match instance.call_execute(store, &context) {
-                Ok(response) => response,
-                Err(err) => {
-                    if let Some(wit_err) = err.downcast_ref::<AppError>() {
-                        Err(wit_err.clone())
-                    } else {
-                        Err(AppError {
-                            code: ErrorCode::InternalError,
-                            message: format!("Unexpected error: {:?}", err),
-                        })
-                    }
-                }
-            }
-        } else {
-            Err(AppError {
-                code: ErrorCode::InternalError,
-                message: "App instance couldn't be initialized".to_string(),
-            })
-        }
+    fn execute_action(&self, context: ActionContext, timeout_seconds: f64) -> Result<ActionResponse, InvocationError> {
+        let invocation = self.snapshot().invoke("Action", &context.connection.id, timeout_seconds, |instance, store| {
+            instance.call_execute(store, &context)
+        })?;
+
+        Ok(ActionResponse {
+            fuel_consumed: invocation.fuel_consumed,
+            http_log: invocation.http_log,
+            ..invocation.response
+        })
     }
 }
@@ -0,0 +1,521 @@
+use magnus::error::ErrorType;
+use magnus::r_hash::ForEach;
+use magnus::value::Opaque;
+use magnus::{gc, prelude::*, Error, RClass, RHash, Ruby, Symbol, TryConvert, Value};
+use ipnet::IpNet;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cassette::{Cassette, CassetteMode};
+use crate::component::EngineOptions;
+use crate::egress::EgressPolicy;
+use crate::http_cache::HttpCache;
+use crate::http_options::{ClientIdentity, ClientOptions, HttpOptions};
+use crate::limits::ResourceLimits;
+use crate::pool::PoolOptions;
+use crate::redact::Redactor;
+use crate::retry::RetryPolicy;
+use crate::ruby_http_cache::RubyHttpCache;
+use crate::ruby_transport::RubyTransport;
+use crate::transport::{ReqwestTransport, Transport};
+
+/// Options `AppBridge::App.new` accepts besides `environment_variables`.
+pub const OPTIONS: &[&str] = &[
+    "cache_dir",
+    "fuel",
+    "max_memory",
+    "max_table_elements",
+    "max_instances",
+    "max_tables",
+    "isolation",
+    "pool_size",
+    "recycle_after_invocations",
+    "recycle_above_memory",
+    "http_timeout",
+    "egress",
+    "http_cassette",
+    "mode",
+    "http_transport",
+    "http_retry",
+    "http_client",
+    "http_max_response_size",
+    "http_max_parallelism",
+    "http_redact",
+    "http_cache",
+];
+
+/// Values of the `isolation` option.
+pub const ISOLATION_MODES: &[&str] = &["shared", "invocation"];
+
+/// Values of the `mode` option.
+pub const CASSETTE_MODES: &[&str] = &["record", "replay"];
+
+/// Keys of the `egress` option.
+pub const EGRESS_OPTIONS: &[&str] = &[
+    "allow_hosts",
+    "deny_hosts",
+    "allow_cidrs",
+    "deny_cidrs",
+    "allow_ports",
+    "allow_private_networks",
+];
+
+/// Keys of the `http_client` option.
+pub const HTTP_CLIENT_OPTIONS: &[&str] = &[
+    "proxy",
+    "root_certificates",
+    "client_certificate",
+    "client_key",
+    "max_redirects",
+    "user_agent",
+];
+
+/// Keys of the `http_redact` option.
+pub const HTTP_REDACT_OPTIONS: &[&str] = &["query_params", "headers"];
+
+/// Keys of the `http_retry` option.
+pub const HTTP_RETRY_OPTIONS: &[&str] = &["max_attempts", "initial_backoff", "max_backoff"];
+
+/// Defines the lists above on `AppBridge::App` as frozen arrays of symbols,
+/// e.g. `App::OPTIONS`.
+pub fn define_constants(ruby: &Ruby, class: RClass) -> Result<(), Error> {
+    let lists = [
+        ("OPTIONS", OPTIONS),
+        ("ISOLATION_MODES", ISOLATION_MODES),
+        ("CASSETTE_MODES", CASSETTE_MODES),
+        ("EGRESS_OPTIONS", EGRESS_OPTIONS),
+        ("HTTP_CLIENT_OPTIONS", HTTP_CLIENT_OPTIONS),
+        ("HTTP_REDACT_OPTIONS", HTTP_REDACT_OPTIONS),
+        ("HTTP_RETRY_OPTIONS", HTTP_RETRY_OPTIONS),
+    ];
+    for (name, values) in lists {
+        let array = ruby.ary_from_iter(values.iter().map(|value| ruby.to_symbol(value)));
+        array.freeze();
+        class.const_set(name, array)?;
+    }
+    Ok(())
+}
+
+/// How invocations share component instances.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Isolation {
+    /// Invocations run in the app's pool of long-lived instances.
+    #[default]
+    Shared,
+    /// Every `fetch_events` and `execute_action` call runs in a fresh
+    /// instance and store, so no state leaks between calls.
+    Invocation,
+}
+
+impl TryConvert for Isolation {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let name: String = val.funcall("to_s", ())?;
+
+        match name.as_str() {
+            "shared" => Ok(Self::Shared),
+            "invocation" => Ok(Self::Invocation),
+            _ => Err(Error::new(
+                magnus::exception::arg_error(),
+                format!("invalid isolation: {} (expected :shared or :invocation)", name),
+            )),
+        }
+    }
+}
+
+impl TryConvert for CassetteMode {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let name: String = val.funcall("to_s", ())?;
+
+        match name.as_str() {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            _ => Err(Error::new(
+                magnus::exception::arg_error(),
+                format!("invalid mode: {} (expected :record or :replay)", name),
+            )),
+        }
+    }
+}
+
+impl TryConvert for EgressPolicy {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let hash = options_hash(val, "egress", EGRESS_OPTIONS)?;
+        let allow_cidrs: Option<Vec<String>> = fetch_option(hash, "allow_cidrs")?;
+        let deny_cidrs: Option<Vec<String>> = fetch_option(hash, "deny_cidrs")?;
+
+        Ok(Self {
+            allow_hosts: fetch_option(hash, "allow_hosts")?.unwrap_or_default(),
+            deny_hosts: fetch_option(hash, "deny_hosts")?.unwrap_or_default(),
+            allow_cidrs: parse_cidrs(allow_cidrs.unwrap_or_default())?,
+            deny_cidrs: parse_cidrs(deny_cidrs.unwrap_or_default())?,
+            allow_ports: fetch_option(hash, "allow_ports")?.unwrap_or_default(),
+            allow_private_networks: fetch_option(hash, "allow_private_networks")?.unwrap_or(false),
+        })
+    }
+}
+
+impl TryConvert for Redactor {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let hash = options_hash(val, "http_redact", HTTP_REDACT_OPTIONS)?;
+
+        Ok(Self::new(
+            fetch_option(hash, "query_params")?.unwrap_or_default(),
+            fetch_option(hash, "headers")?.unwrap_or_default(),
+        ))
+    }
+}
+
+impl TryConvert for RetryPolicy {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let hash = options_hash(val, "http_retry", HTTP_RETRY_OPTIONS)?;
+        let defaults = Self::default();
+
+        Ok(Self {
+            max_attempts: fetch_positive(hash, "max_attempts")?.unwrap_or(defaults.max_attempts),
+            initial_backoff: fetch_duration(hash, "initial_backoff")?.unwrap_or(defaults.initial_backoff),
+            max_backoff: fetch_duration(hash, "max_backoff")?.unwrap_or(defaults.max_backoff),
+        })
+    }
+}
+
+impl TryConvert for ClientOptions {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        let hash = options_hash(val, "http_client", HTTP_CLIENT_OPTIONS)?;
+        let root_certificates: Option<Vec<String>> = fetch_option(hash, "root_certificates")?;
+        let client_certificate: Option<String> = fetch_option(hash, "client_certificate")?;
+        let client_key: Option<String> = fetch_option(hash, "client_key")?;
+
+        let identity = match (client_certificate, client_key) {
+            (Some(certificate), Some(key)) => Some(ClientIdentity {
+                certificate: read_pem(certificate)?,
+                key: read_pem(key)?,
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    "client_certificate and client_key must be given together",
+                ))
+            }
+        };
+
+        Ok(Self {
+            proxy: fetch_option(hash, "proxy")?,
+            root_certificates: root_certificates
+                .unwrap_or_default()
+                .into_iter()
+                .map(read_pem)
+                .collect::<Result<_, _>>()?,
+            identity,
+            max_redirects: fetch_option(hash, "max_redirects")?,
+            user_agent: fetch_option(hash, "user_agent")?,
+        })
+    }
+}
+
+/// Reads PEM data given inline or as the path of a PEM file.
+fn read_pem(value: String) -> Result<Vec<u8>, Error> {
+    if value.trim_start().starts_with("-----BEGIN") {
+        return Ok(value.into_bytes());
+    }
+
+    std::fs::read(&value).map_err(|e| {
+        Error::new(magnus::exception::arg_error(), format!("failed to read {}: {}", value, e))
+    })
+}
+
+/// Parses address ranges such as "10.0.0.0/8". A bare address is a range of
+/// one.
+fn parse_cidrs(cidrs: Vec<String>) -> Result<Vec<IpNet>, Error> {
+    cidrs
+        .into_iter()
+        .map(|cidr| {
+            cidr.parse::<IpNet>()
+                .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| Error::new(magnus::exception::arg_error(), format!("invalid CIDR: {}", cidr)))
+        })
+        .collect()
+}
+
+/// Ruby objects the host calls, such as an `http_transport` or
+/// `http_cache`. The app that holds them marks them, which keeps them alive
+/// and in place for as long as the app lives.
+#[derive(Default, Clone)]
+pub struct RubyValues(Vec<Opaque<Value>>);
+
+impl RubyValues {
+    fn push(&mut self, value: Value) {
+        self.0.push(value.into());
+    }
+
+    pub fn extend(&mut self, other: &RubyValues) {
+        self.0.extend_from_slice(&other.0);
+    }
+
+    pub fn mark(&self, marker: &gc::Marker) {
+        for value in &self.0 {
+            marker.mark(*value);
+        }
+    }
+}
+
+impl fmt::Debug for RubyValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RubyValues({})", self.0.len())
+    }
+}
+
+/// Options given to `AppBridge::App.new`, passed from Ruby as a hash.
+#[derive(Debug, Default, Clone)]
+pub struct AppOptions {
+    /// Directory where precompiled components are cached.
+    pub cache_dir: Option<PathBuf>,
+    /// Fuel budget for each `fetch_events` and `execute_action` call.
+    /// Enables fuel metering when set.
+    pub fuel: Option<u64>,
+    /// Memory and table limits for the component's store.
+    pub limits: ResourceLimits,
+    /// Whether invocations get a fresh instance.
+    pub isolation: Isolation,
+    /// Size and recycling policy of the instance pool.
+    pub pool: PoolOptions,
+    /// Settings for the HTTP requests the component makes.
+    pub http: HttpOptions,
+    /// The Ruby objects `http` calls.
+    pub ruby_values: RubyValues,
+}
+
+impl AppOptions {
+    pub fn engine_options(&self) -> EngineOptions {
+        EngineOptions {
+            fuel: self.fuel.is_some(),
+        }
+    }
+}
+
+impl TryConvert for AppOptions {
+    fn try_convert(val: Value) -> Result<Self, Error> {
+        if val.is_nil() {
+            return Ok(Self::default());
+        }
+
+        let hash = options_hash(val, "options", OPTIONS)?;
+        let cache_dir = fetch_path(hash, "cache_dir")?;
+        let fuel: Option<u64> = fetch_option(hash, "fuel")?;
+        let limits = ResourceLimits {
+            max_memory: fetch_option(hash, "max_memory")?,
+            max_table_elements: fetch_option(hash, "max_table_elements")?,
+            max_instances: fetch_option(hash, "max_instances")?,
+            max_tables: fetch_option(hash, "max_tables")?,
+        };
+
+        let isolation: Option<Isolation> = fetch_option(hash, "isolation")?;
+        let pool_size: Option<usize> = fetch_option(hash, "pool_size")?;
+        let pool = PoolOptions {
+            size: pool_size.unwrap_or(1),
+            max_invocations: fetch_option(hash, "recycle_after_invocations")?,
+            max_memory: fetch_option(hash, "recycle_above_memory")?,
+        };
+
+        let egress: Option<EgressPolicy> = fetch_option(hash, "egress")?;
+        let transport: Option<Value> = fetch_option(hash, "http_transport")?;
+        if transport.is_some_and(|transport| !transport.respond_to("call", false).unwrap_or(false)) {
+            return Err(Error::new(
+                magnus::exception::arg_error(),
+                "http_transport must respond to call",
+            ));
+        }
+        let cache: Option<Value> = fetch_option(hash, "http_cache")?;
+        let store = |cache: Value| ["read", "write"].iter().all(|method| cache.respond_to(*method, false).unwrap_or(false));
+        if cache.is_some_and(|cache| !store(cache)) {
+            return Err(Error::new(
+                magnus::exception::arg_error(),
+                "http_cache must respond to read and write",
+            ));
+        }
+        let client: Option<ClientOptions> = fetch_option(hash, "http_client")?;
+        if let (Some(client), Some(_)) = (&client, transport) {
+            let ignored = built_in_client_options(client);
+            if !ignored.is_empty() {
+                return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    format!("http_client option(s) {} don't apply to http_transport", ignored.join(", ")),
+                ));
+            }
+        }
+        let cassette_path = fetch_path(hash, "http_cassette")?;
+        let cassette_mode: Option<CassetteMode> = fetch_option(hash, "mode")?;
+        if cassette_mode.is_some() && cassette_path.is_none() {
+            return Err(Error::new(magnus::exception::arg_error(), "mode requires http_cassette"));
+        }
+        let cassette = cassette_path
+            .map(|path| Cassette::open(path, cassette_mode.unwrap_or(CassetteMode::Replay)))
+            .transpose()
+            .map_err(|message| Error::new(magnus::exception::arg_error(), message))?;
+        let egress = Arc::new(egress.unwrap_or_default());
+        let client = client.unwrap_or_default();
+        // One client serves every store of the app, and building it here
+        // reports invalid certificates and proxies to `App.new`.
+        let mut ruby_values = RubyValues::default();
+        let transport: Arc<dyn Transport> = match transport {
+            Some(callable) => {
+                ruby_values.push(callable);
+                Arc::new(RubyTransport::new(callable))
+            }
+            None => Arc::new(
+                ReqwestTransport::new(&egress, &client)
+                    .map_err(|message| Error::new(magnus::exception::arg_error(), message))?,
+            ),
+        };
+        let http = HttpOptions {
+            timeout: fetch_duration(hash, "http_timeout")?,
+            retry: fetch_option(hash, "http_retry")?,
+            max_response_size: fetch_positive(hash, "http_max_response_size")?,
+            max_parallelism: fetch_positive(hash, "http_max_parallelism")?,
+            egress,
+            redactor: Arc::new(fetch_option(hash, "http_redact")?.unwrap_or_default()),
+            cassette: cassette.map(Arc::new),
+            cache: cache.map(|store| {
+                ruby_values.push(store);
+                Arc::new(RubyHttpCache::new(store)) as Arc<dyn HttpCache>
+            }),
+            transport: Some(transport),
+            client,
+        };
+
+        Ok(Self {
+            cache_dir: cache_dir.map(PathBuf::from),
+            fuel,
+            limits,
+            isolation: isolation.unwrap_or_default(),
+            pool,
+            http,
+            ruby_values,
+        })
+    }
+}
+
+/// The `http_client` options only the built-in client uses.
+fn built_in_client_options(client: &ClientOptions) -> Vec<&'static str> {
+    let mut options = Vec::new();
+    if client.proxy.is_some() {
+        options.push("proxy");
+    }
+    if !client.root_certificates.is_empty() {
+        options.push("root_certificates");
+    }
+    if client.identity.is_some() {
+        options.extend(["client_certificate", "client_key"]);
+    }
+    if client.max_redirects.is_some() {
+        options.push("max_redirects");
+    }
+    options
+}
+
+/// Converts the `name` option, a hash that may only have `keys`, accepting
+/// both symbol and string keys. `name` is "options" for the options of
+/// `App.new` themselves.
+fn options_hash(val: Value, name: &str, keys: &[&str]) -> Result<RHash, Error> {
+    let hash = RHash::try_convert(val)
+        .map_err(|_| Error::new(magnus::exception::arg_error(), format!("invalid {}: {}", name, val.inspect())))?;
+
+    let mut unknown = Vec::new();
+    hash.foreach(|key: Value, _: Value| {
+        let key: String = key.funcall("to_s", ())?;
+        if !keys.contains(&key.as_str()) {
+            unknown.push(key);
+        }
+        Ok(ForEach::Continue)
+    })?;
+
+    if unknown.is_empty() {
+        return Ok(hash);
+    }
+    let kind = match name {
+        "options" => "option".to_string(),
+        name => format!("{} option", name),
+    };
+    Err(Error::new(
+        magnus::exception::arg_error(),
+        format!("unknown {}(s): {}", kind, unknown.join(", ")),
+    ))
+}
+
+/// Fetches an optional value from an options hash, accepting both symbol
+/// and string keys. `nil` values are treated as missing. Values that don't
+/// convert raise an error naming `key`.
+pub fn fetch_option<T: TryConvert>(hash: RHash, key: &str) -> Result<Option<T>, Error> {
+    let value = hash
+        .get(Symbol::new(key))
+        .or_else(|| hash.get(key));
+
+    match value {
+        Some(value) if !value.is_nil() => TryConvert::try_convert(value)
+            .map(Some)
+            .map_err(|error| naming(key, error)),
+        _ => Ok(None),
+    }
+}
+
+/// Names `key` in a conversion error such as "no implicit conversion of
+/// String into Integer". Argument errors are raised by the options' own
+/// conversions, which say what they reject, and are left as they are.
+fn naming(key: &str, error: Error) -> Error {
+    if error.is_kind_of(magnus::exception::arg_error()) {
+        return error;
+    }
+
+    let message = match error.error_type() {
+        ErrorType::Error(_, message) => message.to_string(),
+        ErrorType::Exception(exception) => exception.to_string(),
+        ErrorType::Jump(_) => return error,
+    };
+    let class = if error.is_kind_of(magnus::exception::type_error()) {
+        magnus::exception::type_error()
+    } else {
+        magnus::exception::arg_error()
+    };
+    Error::new(class, format!("invalid {}: {}", key, message))
+}
+
+/// Fetches a positive number.
+fn fetch_positive<T>(hash: RHash, key: &str) -> Result<Option<T>, Error>
+where
+    T: TryConvert + Default + PartialOrd + fmt::Display,
+{
+    match fetch_option::<T>(hash, key)? {
+        Some(value) if value <= T::default() => Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("invalid {}: {}", key, value),
+        )),
+        value => Ok(value),
+    }
+}
+
+/// Fetches a duration given in seconds.
+fn fetch_duration(hash: RHash, key: &str) -> Result<Option<Duration>, Error> {
+    fetch_option::<f64>(hash, key)?
+        .map(|secs| {
+            Duration::try_from_secs_f64(secs).map_err(|_| {
+                Error::new(
+                    magnus::exception::arg_error(),
+                    format!("invalid {}: {} (expected a non-negative number of seconds)", key, secs),
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Fetches a path, given as a string or anything with `to_path` such as a
+/// `Pathname`.
+fn fetch_path(hash: RHash, key: &str) -> Result<Option<String>, Error> {
+    match fetch_option::<Value>(hash, key)? {
+        Some(path) if path.respond_to("to_path", false)? => path.funcall("to_path", ()).map(Some),
+        Some(path) => String::try_convert(path).map(Some).map_err(|error| naming(key, error)),
+        None => Ok(None),
+    }
+}
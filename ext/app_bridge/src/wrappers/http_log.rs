@@ -0,0 +1,25 @@
+use magnus::{Error, RArray, RHash, Ruby};
+use crate::http_log::HttpLogEntry;
+
+/// Converts an HTTP log to an array of hashes with symbol keys, e.g.
+/// `{ method: "GET", url: "https://...", status: 200, duration_ms: 12.5,
+/// request_size: 0, response_size: 512, error: nil }`.
+pub fn http_log_array(ruby: &Ruby, log: &[HttpLogEntry]) -> Result<RArray, Error> {
+    let array = ruby.ary_new();
+    for entry in log {
+        array.push(entry_hash(ruby, entry)?)?;
+    }
+    Ok(array)
+}
+
+fn entry_hash(ruby: &Ruby, entry: &HttpLogEntry) -> Result<RHash, Error> {
+    let hash = ruby.hash_new();
+    hash.aset(ruby.to_symbol("method"), entry.method.as_str())?;
+    hash.aset(ruby.to_symbol("url"), entry.url.as_str())?;
+    hash.aset(ruby.to_symbol("status"), entry.status)?;
+    hash.aset(ruby.to_symbol("duration_ms"), entry.duration.as_secs_f64() * 1000.0)?;
+    hash.aset(ruby.to_symbol("request_size"), entry.request_size)?;
+    hash.aset(ruby.to_symbol("response_size"), entry.response_size)?;
+    hash.aset(ruby.to_symbol("error"), entry.error.as_deref())?;
+    Ok(hash)
+}
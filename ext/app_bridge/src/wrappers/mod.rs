@@ -5,3 +5,5 @@ pub mod trigger_event;
 pub mod action_context;
 pub mod action_response;
 pub mod app;
+pub mod app_options;
+pub mod http_log;
@@ -1,5 +1,6 @@
-use magnus::{Error, Ruby, TryConvert};
+use magnus::{Error, RArray, Ruby, TryConvert};
 use crate::types::TriggerResponse;
+use super::http_log::http_log_array;
 use super::trigger_event::RTriggerEvent;
 
 #[magnus::wrap(class = "AppBridge::TriggerResponse")]
@@ -18,6 +19,8 @@ impl RTriggerResponse {
         let inner = TriggerResponse {
             store,
             events: res.iter().map(|e| e.into()).collect(),
+            fuel_consumed: None,
+            http_log: Vec::new(),
         };
         Self { inner }
     }
@@ -26,6 +29,16 @@ impl RTriggerResponse {
         self.inner.store.clone()
     }
 
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        self.inner.fuel_consumed
+    }
+
+    /// The HTTP requests the invocation made, oldest first.
+    pub fn http_log(&self) -> Result<RArray, Error> {
+        let ruby = Ruby::get().unwrap();
+        http_log_array(&ruby, &self.inner.http_log)
+    }
+
     pub fn events(&self) -> magnus::RArray {
         let ruby = Ruby::get().unwrap();
         let array = ruby.ary_new();
@@ -3,15 +3,28 @@
 require_relative "app_bridge/version"
 require_relative "app_bridge/app"
 require_relative "app_bridge/file_processor"
+require_relative "app_bridge/http_transport"
 
 # Communication layer for Standout integration apps using WebAssembly components.
 module AppBridge
-  class Error < StandardError; end
+  # Base class of the errors raised by apps.
+  class Error < StandardError
+    attr_writer :http_log
+
+    # The HTTP requests the invocation made before failing, as hashes with
+    # :method, :url, :status, :duration_ms, :request_size, :response_size and
+    # :error keys.
+    def http_log
+      @http_log || []
+    end
+  end
   class TimeoutError < Error; end
   class TooManyEventsError < Error; end
   class StoreTooLargeError < Error; end
   class ActionResponseTooLargeError < Error; end
   class InternalError < Error; end
+  class FuelExhaustedError < Error; end
+  class ResourceLimitError < Error; end
 
   class << self
     # Configurable file uploader callback.
@@ -1,24 +1,102 @@
 # frozen_string_literal: true
 
 require "json"
-require "timeout"
 
 module AppBridge
   # An app that can be used to fetch events and execute actions.
   class App
-    def initialize(component_path, environment_variables: {})
+    # Compiles the component ahead of time and stores the compiled artifact in
+    # +cache_dir+. Apps created with the same +cache_dir+ load the artifact
+    # instead of compiling the component again.
+    #
+    # Pass the same +fuel+ option as the apps that should use the artifact,
+    # since fuel metering changes the compiled code.
+    #
+    # @return [String] the path of the compiled artifact
+    def self.precompile(component_path, cache_dir:, fuel: nil)
+      _rust_precompile(component_path.to_s, cache_dir.to_s, { fuel: fuel })
+    end
+
+    # The options +new+ accepts besides +environment_variables+ are listed in
+    # OPTIONS, the keys of its hash options in EGRESS_OPTIONS,
+    # HTTP_CLIENT_OPTIONS, HTTP_REDACT_OPTIONS and HTTP_RETRY_OPTIONS, and the
+    # values of +isolation+ and +mode+ in ISOLATION_MODES and CASSETTE_MODES.
+    # The extension defines these constants and validates the options,
+    # raising ArgumentError or TypeError naming the option at fault.
+    #
+    # @param component_path [String] path to the wasm component
+    # @param environment_variables [Hash{String => String}] variables exposed
+    #   to the component through the environment interface
+    # @param options [Hash]
+    # @option options [String] :cache_dir directory for precompiled components
+    # @option options [Integer] :fuel fuel budget for each +fetch_events+ and
+    #   +execute_action+ call. Enables fuel metering; exhausting the budget
+    #   raises FuelExhaustedError.
+    # @option options [Integer] :max_memory maximum size in bytes of each
+    #   linear memory of the component
+    # @option options [Integer] :max_table_elements maximum number of elements
+    #   in each table of the component
+    # @option options [Integer] :max_instances maximum number of core instances
+    # @option options [Integer] :max_tables maximum number of tables
+    # @option options [Symbol] :isolation +:shared+ (default) runs every call
+    #   in one long-lived instance; +:invocation+ gives each +fetch_events+
+    #   and +execute_action+ call a fresh instance so no state is carried over
+    # @option options [Integer] :pool_size number of warm instances kept for
+    #   concurrent calls (default 1)
+    # @option options [Integer] :recycle_after_invocations replace a pooled
+    #   instance after it served this many calls
+    # @option options [Integer] :recycle_above_memory replace a pooled
+    #   instance once its linear memory grew past this many bytes
+    # @option options [Numeric] :http_timeout timeout in seconds for HTTP
+    #   requests the component makes, unless a request sets its own
+    # @option options [Hash] :egress which servers the component may connect
+    #   to, see EGRESS_OPTIONS. Loopback, private and link-local addresses are
+    #   blocked unless +allow_private_networks+ is set or +allow_cidrs+ covers
+    #   them.
+    # @option options [String] :http_cassette JSON file to record the
+    #   component's HTTP traffic to, or to replay it from
+    # @option options [Symbol] :mode +:replay+ (default) answers requests from
+    #   +http_cassette+ without touching the network; +:record+ sends them
+    #   and writes each request and response to it
+    # @option options [#call] :http_transport sends the component's HTTP
+    #   requests instead of the built-in client. Called as
+    #   +call(method, url, headers, body)+, it returns +[status, headers, body]+.
+    # @option options [Hash] :http_retry retries idempotent requests answered
+    #   with 429, 502, 503 or 504, unless a request sets its own policy. See
+    #   HTTP_RETRY_OPTIONS; +max_attempts+ defaults to 3, +initial_backoff+ to
+    #   0.5 and +max_backoff+ to 10 seconds.
+    # @option options [Hash] :http_client how the built-in client connects,
+    #   see HTTP_CLIENT_OPTIONS. Certificates and keys are PEM, given inline
+    #   or as file paths. Only +user_agent+ applies with +http_transport+.
+    # @option options [Integer] :http_max_response_size largest response body
+    #   in bytes the component may receive. Larger ones fail the request.
+    # @option options [Integer] :http_max_parallelism most requests of a
+    #   +send-all+ batch sent at the same time (default 8). Batches are sent
+    #   one request at a time with +http_transport+.
+    # @option options [Hash] :http_redact more secrets to redact from request
+    #   errors and the HTTP log, see HTTP_REDACT_OPTIONS: +query_params+ with
+    #   any of the given strings as a word of their names, and the values of +headers+.
+    # @option options [#read, #write] :http_cache store for caching GET
+    #   responses per connection, e.g. +Rails.cache+. Cached requests are
+    #   sent with +If-None-Match+ and +If-Modified-Since+, and a 304 is
+    #   answered with the cached response.
+    #
+    # Exceeding +max_memory+ or +max_table_elements+ while the component runs
+    # raises ResourceLimitError, and so does instantiating a component that
+    # needs more than +max_instances+ or +max_tables+.
+    def initialize(component_path, environment_variables: {}, **options)
       @component_path = component_path
       @environment_variables = environment_variables
-      _rust_initialize(component_path, environment_variables)
-    rescue StandardError
-      raise InternalError, "Incompatible WASM file version"
+      initialize_component(options)
     end
 
     def fetch_events(context)
       response = request_events_with_timeout(context)
 
-      validate_number_of_events!(response.events)
-      validate_store_size!(response.store)
+      with_http_log(response) do
+        validate_number_of_events!(response.events)
+        validate_store_size!(response.store)
+      end
 
       response
     end
@@ -26,13 +104,15 @@ module AppBridge
     def execute_action(context)
       response = request_action_with_timeout(context)
 
-      # Process files: find file-output fields in schema and replace with blob IDs
-      processed_output = process_files(response.serialized_output, context)
+      with_http_log(response) do
+        # Process files: find file-output fields in schema and replace with blob IDs
+        processed_output = process_files(response.serialized_output, context)
 
-      validate_action_response_size!(processed_output)
+        validate_action_response_size!(processed_output)
 
-      # Return new response with processed output
-      response.with_output(processed_output)
+        # Return new response with processed output
+        response.with_output(processed_output)
+      end
     end
 
     def timeout_seconds
@@ -41,6 +121,23 @@ module AppBridge
 
     private
 
+    def initialize_component(options)
+      _rust_initialize(@component_path, @environment_variables, options)
+    rescue ArgumentError, TypeError, ResourceLimitError
+      raise
+    rescue StandardError => e
+      raise InternalError, "Incompatible WASM file version: #{e.message}"
+    end
+
+    # Errors raised after the component returned carry the HTTP requests it
+    # made, like the errors raised by the component itself.
+    def with_http_log(response)
+      yield
+    rescue Error => e
+      e.http_log = response.http_log
+      raise
+    end
+
     def validate_number_of_events!(events)
       return if events.size <= 100
 
@@ -59,16 +156,14 @@ module AppBridge
       raise ActionResponseTooLargeError, "Action response size exceeds 64 kB limit"
     end
 
+    # The deadline is enforced inside the wasm runtime, which interrupts the
+    # component and raises TimeoutError once +timeout_seconds+ have passed.
     def request_action_with_timeout(context)
-      Timeout.timeout(timeout_seconds, TimeoutError, "Action exceeded #{timeout_seconds} seconds") do
-        _rust_execute_action(context)
-      end
+      _rust_execute_action(context, timeout_seconds)
     end
 
     def request_events_with_timeout(context)
-      Timeout.timeout(timeout_seconds, TimeoutError, "Polling exceeded #{timeout_seconds} seconds") do
-        _rust_fetch_events(context)
-      end
+      _rust_fetch_events(context, timeout_seconds)
     end
 
     # Process files in the response based on output schema
@@ -0,0 +1,20 @@
+# frozen_string_literal: true
+
+require "timeout"
+
+module AppBridge
+  # Calls the +http_transport+ callable of an app on behalf of the extension.
+  module HttpTransport
+    # Calls +callable+ with the request, giving up with Timeout::Error once
+    # +timeout+ seconds have passed. The timeout is the request's own or the
+    # app's +http_timeout+, capped at the time left in the invocation.
+    #
+    # @param timeout [Float, nil] seconds the request may take, if limited
+    # @return [Array] +[status, headers, body]+ as returned by +callable+
+    def self.call(callable, timeout, method, url, headers, body)
+      return callable.call(method, url, headers, body) unless timeout
+
+      Timeout.timeout(timeout) { callable.call(method, url, headers, body) }
+    end
+  end
+end
@@ -8,6 +8,219 @@ RSpec.describe AppBridge::App do
     let(:test_env_vars) { ENV["APP_BRIDGE_TEST_MODE"] ? { "APP_BRIDGE_TEST_MODE" => ENV["APP_BRIDGE_TEST_MODE"] } : {} }
     subject(:app) { AppBridge::App.new(component_path, environment_variables: test_env_vars) }
 
+    describe ".precompile" do
+      let(:cache_dir) { Dir.mktmpdir("app_bridge_cache") }
+
+      after { FileUtils.remove_entry(cache_dir) }
+
+      it "writes a compiled artifact to the cache directory" do
+        artifact = AppBridge::App.precompile(component_path, cache_dir: cache_dir)
+
+        expect(File).to exist(artifact)
+        expect(File.dirname(artifact)).to eq(cache_dir)
+      end
+
+      it "lets new apps load the compiled artifact" do
+        AppBridge::App.precompile(component_path, cache_dir: cache_dir)
+        cached_app = AppBridge::App.new(component_path, environment_variables: test_env_vars, cache_dir: cache_dir)
+
+        expect(cached_app.trigger_ids).to include("new-posts")
+        expect(Dir.children(cache_dir).size).to eq(1)
+      end
+    end
+
+    describe ".new" do
+      it "raises an ArgumentError for unknown options" do
+        expect { AppBridge::App.new(component_path, max_memroy: 1) }
+          .to raise_error(ArgumentError, /unknown option\(s\): max_memroy/)
+      end
+
+      it "raises a ResourceLimitError for an instance limit below the component's needs" do
+        expect { AppBridge::App.new(component_path, max_instances: 1) }
+          .to raise_error(AppBridge::ResourceLimitError, /Instance limit exceeded: tried to create 2 instances/)
+      end
+
+      it "raises a ResourceLimitError for a table limit below the component's needs" do
+        expect { AppBridge::App.new(component_path, max_tables: 0) }
+          .to raise_error(AppBridge::ResourceLimitError, /Table count limit exceeded: .* limit is 0 tables/)
+      end
+
+      context "with a memory limit below the component's needs" do
+        subject(:app) do
+          AppBridge::App.new(component_path, environment_variables: test_env_vars, max_memory: 64 * 1024 * 1024)
+        end
+
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::ActionContext.new("complex-input", connection,
+                                       JSON.generate({ customer: { status: "active", orders: [] },
+                                                       padding: "x" * (128 * 1024 * 1024) }))
+        end
+
+        it "raises a ResourceLimitError" do
+          expect { app.execute_action(context) }
+            .to raise_error(AppBridge::ResourceLimitError, /Memory limit exceeded/)
+        end
+
+        it "can be invoked again afterwards" do
+          expect { app.execute_action(context) }.to raise_error(AppBridge::ResourceLimitError)
+          expect(app.action_ids).to include("complex-input")
+        end
+      end
+
+      it "explains why an incompatible component can't be loaded" do
+        Dir.mktmpdir do |dir|
+          path = File.join(dir, "empty.wasm")
+          File.binwrite(path, "\0asm\x0d\0\x01\0")
+
+          expect { AppBridge::App.new(path) }
+            .to raise_error(AppBridge::InternalError, /Component exports no standout:app interfaces/)
+        end
+      end
+
+      it "raises an ArgumentError for a negative HTTP timeout" do
+        expect { AppBridge::App.new(component_path, http_timeout: -1) }
+          .to raise_error(ArgumentError, /invalid http_timeout: -1/)
+      end
+
+      it "raises an ArgumentError for an invalid egress CIDR" do
+        expect { AppBridge::App.new(component_path, egress: { allow_cidrs: ["10.0.0.0/33"] }) }
+          .to raise_error(ArgumentError, %r{invalid CIDR: 10.0.0.0/33})
+      end
+
+      it "raises an ArgumentError for an unknown egress option" do
+        expect { AppBridge::App.new(component_path, egress: { allow_everything: true }) }
+          .to raise_error(ArgumentError, /unknown egress option\(s\): allow_everything/)
+      end
+
+      it "raises an ArgumentError for a cassette mode without a cassette" do
+        expect { AppBridge::App.new(component_path, mode: :replay) }
+          .to raise_error(ArgumentError, /mode requires http_cassette/)
+      end
+
+      it "raises an ArgumentError for a missing cassette in replay mode" do
+        expect { AppBridge::App.new(component_path, http_cassette: "missing.json", mode: :replay) }
+          .to raise_error(ArgumentError, /Failed to read HTTP cassette missing.json/)
+      end
+
+      it "raises an ArgumentError for an invalid number of retry attempts" do
+        expect { AppBridge::App.new(component_path, http_retry: { max_attempts: 0 }) }
+          .to raise_error(ArgumentError, /invalid max_attempts: 0/)
+      end
+
+      it "raises an ArgumentError for an unknown retry option" do
+        expect { AppBridge::App.new(component_path, http_retry: { jitter: true }) }
+          .to raise_error(ArgumentError, /unknown http_retry option\(s\): jitter/)
+      end
+
+      it "raises an ArgumentError for an invalid maximum response size" do
+        expect { AppBridge::App.new(component_path, http_max_response_size: 0) }
+          .to raise_error(ArgumentError, /invalid http_max_response_size: 0/)
+      end
+
+      it "raises an ArgumentError for an HTTP cache without read and write" do
+        expect { AppBridge::App.new(component_path, http_cache: Object.new) }
+          .to raise_error(ArgumentError, /http_cache must respond to read and write/)
+      end
+
+      it "raises an ArgumentError for an unknown redaction option" do
+        expect { AppBridge::App.new(component_path, http_redact: { body: true }) }
+          .to raise_error(ArgumentError, /unknown http_redact option\(s\): body/)
+      end
+
+      it "raises an ArgumentError for an invalid maximum parallelism" do
+        expect { AppBridge::App.new(component_path, http_max_parallelism: 0) }
+          .to raise_error(ArgumentError, /invalid http_max_parallelism: 0/)
+      end
+
+      it "raises an ArgumentError for an invalid proxy" do
+        expect { AppBridge::App.new(component_path, http_client: { proxy: "not a proxy" }) }
+          .to raise_error(ArgumentError, /invalid proxy/)
+      end
+
+      it "raises an ArgumentError for a missing root certificate" do
+        expect { AppBridge::App.new(component_path, http_client: { root_certificates: ["/nonexistent/ca.pem"] }) }
+          .to raise_error(ArgumentError, %r{failed to read /nonexistent/ca.pem})
+      end
+
+      it "raises an ArgumentError for a client certificate without a key" do
+        expect { AppBridge::App.new(component_path, http_client: { client_certificate: "cert.pem" }) }
+          .to raise_error(ArgumentError, /client_certificate and client_key must be given together/)
+      end
+
+      it "raises an ArgumentError for client options an HTTP transport ignores" do
+        expect { AppBridge::App.new(component_path, http_transport: ->(*) {}, http_client: { proxy: "http://proxy" }) }
+          .to raise_error(ArgumentError, /http_client option\(s\) proxy don't apply to http_transport/)
+      end
+
+      it "raises an ArgumentError for an HTTP transport that isn't callable" do
+        expect { AppBridge::App.new(component_path, http_transport: "faraday") }
+          .to raise_error(ArgumentError, /http_transport must respond to call/)
+      end
+
+      it "raises an ArgumentError for an unknown isolation mode" do
+        expect { AppBridge::App.new(component_path, isolation: :tenant) }
+          .to raise_error(ArgumentError, /invalid isolation: tenant/)
+      end
+
+      it "raises a TypeError naming an option of the wrong type" do
+        expect { AppBridge::App.new(component_path, fuel: "lots") }
+          .to raise_error(TypeError, /invalid fuel: no implicit conversion/)
+      end
+
+      it "raises an ArgumentError for an options hash that isn't a hash" do
+        expect { AppBridge::App.new(component_path, egress: "strict") }
+          .to raise_error(ArgumentError, /invalid egress: "strict"/)
+      end
+
+      context "with per-invocation isolation" do
+        subject(:app) do
+          AppBridge::App.new(component_path, environment_variables: test_env_vars, isolation: :invocation)
+        end
+
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::TriggerContext.new("new-todos", connection, "", "{}")
+        end
+
+        it "runs every invocation in a fresh instance" do
+          first = app.fetch_events(context)
+          second = app.fetch_events(context)
+
+          expect(second.events.map(&:id)).to eq(first.events.map(&:id))
+          expect(second.store).to eq(first.store)
+        end
+
+        it "can be invoked again after a timeout" do
+          allow(app).to receive(:timeout_seconds).and_return(0.01)
+          expect { app.fetch_events(context) }.to raise_error(AppBridge::TimeoutError)
+
+          allow(app).to receive(:timeout_seconds).and_call_original
+          expect(app.fetch_events(context).events).not_to be_empty
+        end
+      end
+
+      it "lets an HTTP cache be collected along with the app" do
+        store_class = Struct.new(:entries) do
+          def read(key)
+            entries[key]
+          end
+
+          def write(key, value)
+            entries[key] = value
+          end
+        end
+        stores = Array.new(20) do
+          store = store_class.new({})
+          AppBridge::App.new(component_path, http_cache: store)
+          WeakRef.new(store)
+        end
+        GC.start(full_mark: true, immediate_sweep: true)
+
+        expect(stores.count(&:weakref_alive?)).to be < stores.size / 2
+      end
+    end
+
     describe "#trigger_ids" do
       it "returns an array of trigger ids" do
         expect(app.trigger_ids).to be_a(Array)
@@ -35,36 +248,88 @@ RSpec.describe AppBridge::App do
         AppBridge::TriggerContext.new("new-todos", connection, "world", "{}")
       end
 
-      it "returns a response with new store" do
-        response = app.fetch_events(context)
-        expect(response).to be_a(AppBridge::TriggerResponse)
-        expect(response.store).to be_a(String)
-        expect(response.events).not_to be_empty
-        expect(response.events).to all(be_a(AppBridge::TriggerEvent))
+      context "when replaying recorded HTTP traffic" do
+        subject(:app) do
+          AppBridge::App.new(component_path, environment_variables: test_env_vars,
+                                             http_cassette: cassette_path("jsonplaceholder_todos"), mode: :replay)
+        end
+
+        it "returns a response with new store" do
+          response = app.fetch_events(context)
+          expect(response).to be_a(AppBridge::TriggerResponse)
+          expect(response.store).to be_a(String)
+          expect(response.events).not_to be_empty
+          expect(response.events).to all(be_a(AppBridge::TriggerEvent))
+        end
+
+        it "includes trigger events" do
+          response = app.fetch_events(context)
+          expect(response.store).to eq("10")
+          expect(response.events).to include(
+            have_attributes(id: "1", serialized_data: include("delectus aut autem")),
+            have_attributes(id: "2", serialized_data: include("quis ut nam facilis et officia qui")),
+            have_attributes(id: "3", serialized_data: include("fugiat veniam minus"))
+          )
+        end
+
+        it "logs the requests it made" do
+          expect(app.fetch_events(context).http_log).to contain_exactly(
+            include(method: "GET", url: start_with("https://jsonplaceholder.typicode.com/"), status: 200)
+          )
+        end
+
+        it "fails requests that were not recorded" do
+          posts = AppBridge::TriggerContext.new("new-posts", context.connection, "", "{}")
+
+          expect { app.fetch_events(posts) }.to raise_error(AppBridge::Error, /No unplayed interaction/)
+        end
       end
 
-      it "includes trigger events" do
-        response = app.fetch_events(context)
-        expect(response.store).to eq("10")
-        expect(response.events).to include(
-          have_attributes(id: "1", serialized_data: include("delectus aut autem")),
-          have_attributes(id: "2", serialized_data: include("quis ut nam facilis et officia qui")),
-          have_attributes(id: "3", serialized_data: include("fugiat veniam minus"))
-        )
+      context "when recording HTTP traffic" do
+        subject(:app) do
+          AppBridge::App.new(component_path, environment_variables: test_env_vars,
+                                             egress: { allow_cidrs: ["127.0.0.0/8"] },
+                                             http_cassette: cassette, mode: :record)
+        end
+
+        let(:cassette) { File.join(Dir.mktmpdir("app_bridge_cassette"), "todos.json") }
+        let(:server) { LocalHttpServer.new { [200, { "Content-Type" => "text/plain" }, "recorded"] } }
+        let(:action_context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::ActionContext.new("http-get", connection, JSON.generate({ url: server.url("/get") }))
+        end
+
+        after do
+          server.stop
+          FileUtils.remove_entry(File.dirname(cassette))
+        end
+
+        it "writes a cassette that replays without the server" do
+          app.execute_action(action_context)
+          server.stop
+
+          replay = AppBridge::App.new(component_path, egress: { allow_cidrs: ["127.0.0.0/8"] },
+                                                      http_cassette: cassette, mode: :replay)
+          expect(replay.execute_action(action_context).serialized_output).to include("recorded")
+        end
       end
 
       context "when polling takes too long" do
         before do
           allow(app).to receive(:timeout_seconds).and_return(0.01)
-          allow(app).to receive(:_rust_fetch_events).and_wrap_original do |_original_method, *_args|
-            sleep(10) # Simulate a long-running operation
-          end
         end
 
         it "raises a Timeout::Error with a message" do
           expect { app.fetch_events(context) }
             .to raise_error(AppBridge::TimeoutError, /Polling exceeded \d+(\.\d+)? seconds/)
         end
+
+        it "can be invoked again after the timeout" do
+          expect { app.fetch_events(context) }.to raise_error(AppBridge::TimeoutError)
+
+          allow(app).to receive(:timeout_seconds).and_call_original
+          expect(app.fetch_events(context).events).not_to be_empty
+        end
       end
 
       context "when context has more than 100 events" do
@@ -367,6 +632,292 @@ RSpec.describe AppBridge::App do
         expect(response.serialized_output).to include("url")
       end
 
+      it "does not report fuel without a fuel budget" do
+        expect(app.execute_action(context).fuel_consumed).to be_nil
+      end
+
+      context "with a fuel budget" do
+        subject(:app) { AppBridge::App.new(component_path, environment_variables: test_env_vars, fuel: fuel) }
+
+        let(:fuel) { 10_000_000_000 }
+
+        it "reports the fuel consumed" do
+          expect(app.execute_action(context).fuel_consumed).to be_between(1, fuel)
+        end
+
+        context "when the budget is too small" do
+          let(:fuel) { 1 }
+
+          it "raises a FuelExhaustedError" do
+            expect { app.execute_action(context) }
+              .to raise_error(AppBridge::FuelExhaustedError, /Action exceeded its fuel budget of 1/)
+          end
+        end
+      end
+
+      context "when the component calls a server running on another Ruby thread" do
+        subject(:app) { AppBridge::App.new(component_path, egress: local_egress) }
+
+        let(:local_egress) { { allow_cidrs: ["127.0.0.0/8"] } }
+
+        let(:server) do
+          LocalHttpServer.new { [200, { "Content-Type" => "application/json" }, JSON.generate({ from: "ruby" })] }
+        end
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::ActionContext.new("http-get", connection, JSON.generate({ url: server.url("/get") }))
+        end
+
+        after { server.stop }
+
+        it "releases the GVL so the server can respond" do
+          expect(app.execute_action(context).serialized_output).to include("ruby")
+        end
+
+        it "serves calls from several threads at once" do
+          threads = Array.new(3) { Thread.new { AppBridge::App.new(component_path, egress: local_egress).execute_action(context) } }
+
+          expect(threads.map(&:value)).to all(have_attributes(serialized_output: include("ruby")))
+        end
+
+        context "with a pool of instances" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress, pool_size: 3, recycle_after_invocations: 1) }
+
+          it "serves concurrent calls and keeps working as instances are recycled" do
+            2.times do
+              threads = Array.new(3) { Thread.new { app.execute_action(context) } }
+              expect(threads.map(&:value)).to all(have_attributes(serialized_output: include("ruby")))
+            end
+          end
+        end
+
+        context "when the only pooled instance is busy" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress, pool_size: 1) }
+
+          let(:server) do
+            LocalHttpServer.new do
+              sleep 1
+              [200, { "Content-Type" => "application/json" }, JSON.generate({ from: "ruby" })]
+            end
+          end
+
+          it "times out waiting for the instance" do
+            allow(app).to receive(:timeout_seconds).and_return(30, 0.2)
+            busy = Thread.new { app.execute_action(context) }
+            server.requests.pop
+
+            expect { app.execute_action(context) }
+              .to raise_error(AppBridge::TimeoutError, /Action exceeded 0.2 seconds waiting for an instance/)
+            expect(busy.value.serialized_output).to include("ruby")
+          end
+        end
+
+        context "when instances are recycled on memory growth" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress, recycle_above_memory: 1) }
+
+          it "replaces the instance after every call" do
+            expect(app.execute_action(context).serialized_output).to include("ruby")
+            expect(app.execute_action(context).serialized_output).to include("ruby")
+          end
+        end
+
+        it "can be shared between threads" do
+          threads = Array.new(3) { Thread.new { app.execute_action(context) } }
+          ids = Array.new(3) { Thread.new { app.action_ids } }
+
+          expect(threads.map(&:value)).to all(have_attributes(serialized_output: include("ruby")))
+          expect(ids.map(&:value)).to all(include("http-get"))
+        end
+
+        context "without an egress policy allowing the server" do
+          subject(:app) { AppBridge::App.new(component_path) }
+
+          it "blocks the request" do
+            expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /egress policy/)
+            expect(server.requests).to be_empty
+          end
+        end
+
+        context "when the response is larger than the limit" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress, http_max_response_size: 1024) }
+
+          let(:server) { LocalHttpServer.new { [200, { "Content-Type" => "application/json" }, "[#{"1," * 1024}1]"] } }
+
+          it "fails the request" do
+            expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /larger than 1024 bytes/)
+          end
+        end
+
+        context "with a custom user agent" do
+          subject(:app) do
+            AppBridge::App.new(component_path, egress: local_egress, http_client: { user_agent: "Acme/1.0" })
+          end
+
+          it "sends it" do
+            app.execute_action(context)
+
+            expect(server.requests.pop.headers["user-agent"]).to eq("Acme/1.0")
+          end
+        end
+
+        context "with a redirect limit" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress, http_client: { max_redirects: 1 }) }
+
+          let(:server) { LocalHttpServer.new { |request| [302, { "Location" => "#{request.path}/next" }, ""] } }
+
+          it "fails the request after that many redirects" do
+            expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /too many redirects/)
+            expect(server.requests.size).to eq(2)
+          end
+        end
+
+        context "when the server is briefly unavailable" do
+          subject(:app) do
+            AppBridge::App.new(component_path, egress: local_egress, http_retry: { initial_backoff: 0.01 })
+          end
+
+          let(:server) do
+            attempts = 0
+            LocalHttpServer.new do
+              attempts += 1
+              next [503, { "Retry-After" => "0" }, ""] if attempts < 3
+
+              [200, { "Content-Type" => "application/json" }, JSON.generate({ from: "ruby" })]
+            end
+          end
+
+          it "retries the request" do
+            response = app.execute_action(context)
+
+            expect(response.serialized_output).to include("ruby")
+            expect(server.requests.size).to eq(3)
+            expect(response.http_log.map { |entry| entry[:status] }).to eq([503, 503, 200])
+          end
+        end
+
+        context "when the server's host is denied" do
+          subject(:app) { AppBridge::App.new(component_path, egress: local_egress.merge(deny_hosts: ["127.0.0.1"])) }
+
+          it "blocks the request" do
+            expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /host 127.0.0.1 is denied/)
+          end
+        end
+      end
+
+      context "when the server responds slower than the HTTP timeout" do
+        subject(:app) do
+          AppBridge::App.new(component_path, http_timeout: 0.1, egress: { allow_private_networks: true })
+        end
+
+        let(:server) do
+          LocalHttpServer.new do
+            sleep 1
+            [200, { "Content-Type" => "application/json" }, "{}"]
+          end
+        end
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::ActionContext.new("http-get", connection, JSON.generate({ url: server.url("/slow") }))
+        end
+
+        after { server.stop }
+
+        it "fails the request with a timeout" do
+          expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /timed out/)
+        end
+      end
+
+      context "with an HTTP transport" do
+        subject(:app) { AppBridge::App.new(component_path, http_transport: transport) }
+
+        let(:requests) { [] }
+        let(:transport) do
+          lambda do |method, url, headers, body|
+            requests << { method: method, url: url, headers: headers, body: body }
+            [200, { "Content-Type" => "application/json" }, JSON.generate({ from: "transport" })]
+          end
+        end
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          AppBridge::ActionContext.new("http-get", connection, JSON.generate({ url: "https://93.184.215.14/get" }))
+        end
+
+        it "sends the component's requests through the callable" do
+          expect(app.execute_action(context).serialized_output).to include("transport")
+          expect(requests).to contain_exactly(
+            include(method: "GET", url: "https://93.184.215.14/get", headers: include("User-Agent"))
+          )
+        end
+
+        context "when the callable raises" do
+          let(:transport) { ->(*) { raise "connection pool exhausted" } }
+
+          it "fails the request with the error message" do
+            expect { app.execute_action(context) }
+              .to raise_error(AppBridge::Error, /HTTP transport failed for GET.*connection pool exhausted/)
+          end
+        end
+
+        it "keeps the callable alive for as long as the app" do
+          app = AppBridge::App.new(component_path, http_transport: lambda { |*|
+            [200, { "Content-Type" => "application/json" }, JSON.generate({ from: "transport" })]
+          })
+          GC.start
+          GC.compact if GC.respond_to?(:compact)
+
+          expect(app.execute_action(context).serialized_output).to include("transport")
+        end
+
+        it "lets the callable be collected along with the app" do
+          callables = Array.new(20) do
+            callable = ->(*) { [200, {}, "{}"] }
+            AppBridge::App.new(component_path, http_transport: callable)
+            WeakRef.new(callable)
+          end
+          GC.start(full_mark: true, immediate_sweep: true)
+
+          expect(callables.count(&:weakref_alive?)).to be < callables.size / 2
+        end
+
+        it "still applies the egress policy" do
+          internal = AppBridge::ActionContext.new("http-get", context.connection,
+                                                  JSON.generate({ url: "http://169.254.169.254/" }))
+
+          expect { app.execute_action(internal) }.to raise_error(AppBridge::Error, /egress policy/)
+          expect(requests).to be_empty
+        end
+
+        it "checks the addresses host names resolve to" do
+          internal = AppBridge::ActionContext.new("http-get", context.connection,
+                                                  JSON.generate({ url: "http://localhost/admin" }))
+
+          expect { app.execute_action(internal) }
+            .to raise_error(AppBridge::Error, /localhost resolves to internal address/)
+          expect(requests).to be_empty
+        end
+
+        it "logs the requests on the response" do
+          logged = AppBridge::ActionContext.new("http-get", context.connection,
+                                                JSON.generate({ url: "https://93.184.215.14/get?api_key=s3cret" }))
+
+          expect(app.execute_action(logged).http_log).to contain_exactly(
+            include(method: "GET", url: "https://93.184.215.14/get?api_key=REDACTED", status: 200,
+                    request_size: 0, response_size: JSON.generate({ from: "transport" }).bytesize, error: nil)
+          )
+        end
+
+        it "logs the requests on the error" do
+          internal = AppBridge::ActionContext.new("http-get", context.connection,
+                                                  JSON.generate({ url: "http://169.254.169.254/" }))
+
+          expect { app.execute_action(internal) }.to raise_error(AppBridge::Error) { |error|
+            expect(error.http_log).to contain_exactly(
+              include(url: "http://169.254.169.254/", status: nil, error: /egress policy/)
+            )
+          }
+        end
+      end
+
       context "with invalid action ID" do
         let(:context) do
           connection = AppBridge::Connection.new("1", "John Doe",
@@ -379,6 +930,29 @@ RSpec.describe AppBridge::App do
         end
       end
 
+      context "when the server responds slower than the invocation timeout" do
+        subject(:app) { AppBridge::App.new(component_path, egress: { allow_private_networks: true }) }
+
+        # Connections are queued but never accepted, so no response comes.
+        let(:server) { TCPServer.new("127.0.0.1", 0) }
+        let(:context) do
+          connection = AppBridge::Connection.new("1", "John Doe", "{}")
+          url = "http://127.0.0.1:#{server.addr[1]}/slow"
+          AppBridge::ActionContext.new("http-get", connection, JSON.generate({ url: url }))
+        end
+
+        before { allow(app).to receive(:timeout_seconds).and_return(0.2) }
+
+        after { server.close }
+
+        it "gives up on the request when the invocation runs out of time" do
+          started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+
+          expect { app.execute_action(context) }.to raise_error(AppBridge::Error, /timed out|exceeded 0.2 seconds/)
+          expect(Process.clock_gettime(Process::CLOCK_MONOTONIC) - started).to be < 2
+        end
+      end
+
       context "with http-post action" do
         let(:context) do
           connection = AppBridge::Connection.new("1", "John Doe",
@@ -74,6 +74,158 @@ RSpec.describe "Backward Compatibility" do
     end
   end
 
+  describe "v4.2 component (built against WIT 4.2.0)" do
+    let(:app) do
+      AppBridge::App.new(
+        "spec/fixtures/components/rust_app_v4_2.wasm",
+        environment_variables: {},
+        egress: { allow_cidrs: ["127.0.0.0/8"] }
+      )
+    end
+
+    let(:connection) do
+      AppBridge::Connection.new(
+        "conn-123",
+        "Test Connection",
+        '{"api_key": "test-key"}'
+      )
+    end
+
+    def execute(action_id, input)
+      context = AppBridge::ActionContext.new(action_id, connection, JSON.generate(input))
+      JSON.parse(app.execute_action(context).serialized_output)
+    end
+
+    context "#fetch_events" do
+      it "returns trigger events from v4.2 component" do
+        context = AppBridge::TriggerContext.new(
+          "simple-trigger",
+          connection,
+          "{}",
+          "{}"
+        )
+        response = app.fetch_events(context)
+
+        data = JSON.parse(response.events.first.serialized_data)
+        expect(data["message"]).to eq("Hello from v4.2 connector")
+      end
+    end
+
+    context "#execute_action" do
+      let(:server) { LocalHttpServer.new { [200, { "Content-Type" => "text/plain" }, "pong"] } }
+
+      after { server.stop }
+
+      it "sends requests built with the v4.2 builder methods" do
+        context = AppBridge::ActionContext.new(
+          "http-get",
+          connection,
+          JSON.generate({ url: server.url("/ping"), query: { q: "a b" }, timeout_ms: 5000 })
+        )
+        output = JSON.parse(app.execute_action(context).serialized_output)
+
+        expect(output).to include("status" => 200, "body" => "pong", "attempts" => 1)
+        expect(server.requests.pop.path).to eq("/ping?q=a+b")
+      end
+
+      it "sends custom methods" do
+        execute("http-request", { method: "PROPFIND", url: server.url("/calendars") })
+
+        expect(server.requests.pop).to have_attributes(method: "PROPFIND", path: "/calendars")
+      end
+
+      it "sends form bodies" do
+        execute("http-request", { method: "POST", url: server.url("/items"), form: { name: "a b", tag: "x&y" } })
+
+        request = server.requests.pop
+        expect(request.headers["content-type"]).to eq("application/x-www-form-urlencoded")
+        expect(request.body).to eq("name=a+b&tag=x%26y")
+      end
+
+      it "sends multipart bodies" do
+        execute("http-request", { method: "POST", url: server.url("/upload"), multipart: { title: "Report" } })
+
+        request = server.requests.pop
+        expect(request.headers["content-type"]).to start_with("multipart/form-data; boundary=")
+        expect(request.body).to include(%(Content-Disposition: form-data; name="title"\r\n\r\nReport\r\n))
+      end
+
+      it "sends batches of requests with send-all" do
+        urls = [server.url("/a"), server.url("/b"), "http://127.0.0.1:1/closed"]
+        output = execute("http-send-all", { urls: urls, max_parallel: 2 })
+
+        expect(output["responses"].first(2)).to all(eq("status" => 200, "body" => "pong"))
+        expect(output["responses"].last["error"]).to match(/HTTP request failed: Connect/)
+        expect(2.times.map { server.requests.pop.path }).to contain_exactly("/a", "/b")
+      end
+    end
+
+    context "when the server is unavailable at first" do
+      let(:server) do
+        responses = [[503, {}, "busy"], [200, {}, "ok"]]
+        LocalHttpServer.new { responses.shift || [200, {}, "ok"] }
+      end
+
+      after { server.stop }
+
+      it "retries the request with the component's retry policy" do
+        output = execute("http-get", { url: server.url("/events"), max_attempts: 3 })
+
+        expect(output).to include("status" => 200, "body" => "ok", "attempts" => 2)
+      end
+    end
+
+    context "when the egress policy blocks the host" do
+      let(:app) { AppBridge::App.new("spec/fixtures/components/rust_app_v4_2.wasm") }
+      let(:server) { LocalHttpServer.new }
+
+      after { server.stop }
+
+      it "fails the request with a forbidden error" do
+        expect { execute("http-get", { url: server.url("/admin") }) }
+          .to raise_error(AppBridge::ForbiddenError, /HTTP request forbidden/)
+        expect(server.requests).to be_empty
+      end
+    end
+
+    context "with an HTTP transport" do
+      let(:app) do
+        AppBridge::App.new("spec/fixtures/components/rust_app_v4_2.wasm", http_transport: transport)
+      end
+
+      def action_context(input)
+        input = JSON.generate({ url: "https://93.184.215.14/" }.merge(input))
+        AppBridge::ActionContext.new("http-get", connection, input)
+      end
+
+      context "when the callable can't connect" do
+        let(:transport) { ->(*) { raise Errno::ECONNREFUSED } }
+
+        it "fails the request with a connect error" do
+          expect { app.execute_action(action_context({})) }
+            .to raise_error(AppBridge::Error, /Connect\(.*Connection refused/)
+        end
+      end
+
+      context "when the callable is slower than the request's timeout" do
+        let(:transport) do
+          lambda do |*|
+            sleep 5
+            [200, {}, "{}"]
+          end
+        end
+
+        it "gives up on the request after the timeout" do
+          started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+
+          expect { app.execute_action(action_context(timeout_ms: 100)) }
+            .to raise_error(AppBridge::TimeoutError, /HTTP request timed out/)
+          expect(Process.clock_gettime(Process::CLOCK_MONOTONIC) - started).to be < 2
+        end
+      end
+    end
+  end
+
   describe "v4 component (built against WIT 4.0.0)" do
     let(:app) do
       AppBridge::App.new(
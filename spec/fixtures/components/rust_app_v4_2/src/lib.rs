@@ -0,0 +1,360 @@
+// A simple v4.2 connector for backward compatibility testing
+// This uses WIT v4.2 (per-request timeouts, structured request errors,
+// retries, send-all, custom methods, query builders and form bodies)
+
+wit_bindgen::generate!({
+    path: "../../../../ext/app_bridge/wit/v4_2",
+    world: "bridge",
+    with: {},
+});
+
+use std::sync::OnceLock;
+use standout::app::types::{
+    ActionContext, ActionResponse, AppError, ErrorCode, TriggerContext, TriggerEvent,
+    TriggerResponse,
+};
+use standout::app::http::{
+    send_all, Method, MultipartPart, RequestBuilder, RequestError, Response, RetryPolicy, TextPart,
+};
+use serde_json::json;
+
+struct MyApp;
+
+// Thread-safe static storage using OnceLock
+static ACTIONS: OnceLock<Vec<RegisteredAction>> = OnceLock::new();
+static TRIGGERS: OnceLock<Vec<RegisteredTrigger>> = OnceLock::new();
+
+struct RegisteredAction {
+    id: &'static str,
+    handler: fn(ActionContext) -> Result<ActionResponse, AppError>,
+    input_schema: &'static str,
+    output_schema: &'static str,
+}
+
+struct RegisteredTrigger {
+    id: &'static str,
+    handler: fn(TriggerContext) -> Result<TriggerResponse, AppError>,
+    input_schema: &'static str,
+    output_schema: &'static str,
+}
+
+fn get_actions() -> &'static Vec<RegisteredAction> {
+    ACTIONS.get_or_init(|| {
+        let http_input_schema = r#"{
+            "type": "object",
+            "properties": {
+                "url": { "type": "string" },
+                "query": { "type": "object", "additionalProperties": { "type": "string" } },
+                "timeout_ms": { "type": "integer" },
+                "max_attempts": { "type": "integer" }
+            }
+        }"#;
+
+        let http_output_schema = r#"{
+            "type": "object",
+            "properties": {
+                "status": { "type": "integer" },
+                "body": { "type": "string" },
+                "attempts": { "type": "integer" }
+            }
+        }"#;
+
+        let request_input_schema = r#"{
+            "type": "object",
+            "properties": {
+                "method": { "type": "string" },
+                "url": { "type": "string" },
+                "form": { "type": "object", "additionalProperties": { "type": "string" } },
+                "multipart": { "type": "object", "additionalProperties": { "type": "string" } }
+            }
+        }"#;
+
+        let send_all_input_schema = r#"{
+            "type": "object",
+            "properties": {
+                "urls": { "type": "array", "items": { "type": "string" } },
+                "max_parallel": { "type": "integer" }
+            }
+        }"#;
+
+        let send_all_output_schema = r#"{
+            "type": "object",
+            "properties": {
+                "responses": { "type": "array" }
+            }
+        }"#;
+
+        vec![
+            RegisteredAction {
+                id: "http-get",
+                handler: http_action,
+                input_schema: http_input_schema,
+                output_schema: http_output_schema,
+            },
+            RegisteredAction {
+                id: "http-request",
+                handler: http_request_action,
+                input_schema: request_input_schema,
+                output_schema: http_output_schema,
+            },
+            RegisteredAction {
+                id: "http-send-all",
+                handler: http_send_all_action,
+                input_schema: send_all_input_schema,
+                output_schema: send_all_output_schema,
+            },
+        ]
+    })
+}
+
+fn get_triggers() -> &'static Vec<RegisteredTrigger> {
+    TRIGGERS.get_or_init(|| {
+        let trigger_input_schema = r#"{ "type": "object" }"#;
+        let trigger_output_schema =
+            r#"{ "type": "object", "properties": { "message": { "type": "string" } } }"#;
+
+        vec![RegisteredTrigger {
+            id: "simple-trigger",
+            handler: simple_trigger,
+            input_schema: trigger_input_schema,
+            output_schema: trigger_output_schema,
+        }]
+    })
+}
+
+fn parse_input(context: &ActionContext) -> Result<serde_json::Value, AppError> {
+    serde_json::from_str(&context.serialized_input).map_err(|_| AppError {
+        code: ErrorCode::MalformedResponse,
+        message: "Invalid JSON input".to_string(),
+    })
+}
+
+// The string values of an object in the input, as pairs
+fn pairs(input: &serde_json::Value, name: &str) -> Option<Vec<(String, String)>> {
+    input.get(name).and_then(|v| v.as_object()).map(|object| {
+        object
+            .iter()
+            .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+            .collect()
+    })
+}
+
+// Maps the structured request errors to app errors
+fn request_error(error: RequestError) -> AppError {
+    match error {
+        RequestError::Timeout(message) => AppError {
+            code: ErrorCode::Timeout,
+            message: format!("HTTP request timed out: {}", message),
+        },
+        RequestError::Forbidden(message) => AppError {
+            code: ErrorCode::Forbidden,
+            message: format!("HTTP request forbidden: {}", message),
+        },
+        e => AppError {
+            code: ErrorCode::Other,
+            message: format!("HTTP request failed: {:?}", e),
+        },
+    }
+}
+
+fn response_output(response: Response) -> ActionResponse {
+    let output = json!({
+        "status": response.status,
+        "body": response.body,
+        "attempts": response.attempts,
+    });
+
+    ActionResponse {
+        serialized_output: output.to_string(),
+    }
+}
+
+// HTTP action using the 4.2 builder methods: query parameters, a timeout of
+// its own and a retry policy, with no retries by default
+fn http_action(context: ActionContext) -> Result<ActionResponse, AppError> {
+    let input = parse_input(&context)?;
+
+    let url = input
+        .get("url")
+        .and_then(|v| v.as_str())
+        .unwrap_or("https://httpbin.org/get");
+
+    let max_attempts = input.get("max_attempts").and_then(|v| v.as_u64()).unwrap_or(1);
+
+    let mut builder = RequestBuilder::new().url(url).retry(RetryPolicy {
+        max_attempts: max_attempts as u32,
+        initial_backoff_ms: 10,
+        max_backoff_ms: 100,
+    });
+    if let Some(query) = pairs(&input, "query") {
+        builder = builder.queries(&query);
+    }
+    if let Some(timeout) = input.get("timeout_ms").and_then(|v| v.as_u64()) {
+        builder = builder.timeout(timeout as u32);
+    }
+
+    let response = builder.send().map_err(request_error)?;
+
+    Ok(response_output(response))
+}
+
+// HTTP action sending any method, with a form or multipart body
+fn http_request_action(context: ActionContext) -> Result<ActionResponse, AppError> {
+    let input = parse_input(&context)?;
+
+    let method = match input.get("method").and_then(|v| v.as_str()).unwrap_or("GET") {
+        "GET" => Method::Get,
+        "POST" => Method::Post,
+        "PUT" => Method::Put,
+        "DELETE" => Method::Delete,
+        "PATCH" => Method::Patch,
+        "OPTIONS" => Method::Options,
+        "HEAD" => Method::Head,
+        other => Method::Other(other.to_string()),
+    };
+    let url = input.get("url").and_then(|v| v.as_str()).unwrap_or_default();
+
+    let mut builder = RequestBuilder::new().method(&method).url(url);
+    if let Some(fields) = pairs(&input, "form") {
+        builder = builder.form(&fields);
+    }
+    if let Some(fields) = pairs(&input, "multipart") {
+        let parts: Vec<MultipartPart> = fields
+            .into_iter()
+            .map(|(name, value)| MultipartPart::Text(TextPart { name, value }))
+            .collect();
+        builder = builder.multipart(&parts);
+    }
+
+    let response = builder.send().map_err(request_error)?;
+
+    Ok(response_output(response))
+}
+
+// HTTP action fetching several URLs at the same time with send-all
+fn http_send_all_action(context: ActionContext) -> Result<ActionResponse, AppError> {
+    let input = parse_input(&context)?;
+
+    let builders: Vec<RequestBuilder> = input
+        .get("urls")
+        .and_then(|v| v.as_array())
+        .map(|urls| {
+            urls.iter()
+                .map(|url| RequestBuilder::new().url(url.as_str().unwrap_or_default()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let max_parallel = input.get("max_parallel").and_then(|v| v.as_u64()).unwrap_or(4);
+
+    let requests: Vec<&RequestBuilder> = builders.iter().collect();
+    let responses: Vec<serde_json::Value> = send_all(&requests, max_parallel as u32)
+        .into_iter()
+        .map(|result| match result {
+            Ok(response) => json!({ "status": response.status, "body": response.body }),
+            Err(e) => json!({ "error": request_error(e).message }),
+        })
+        .collect();
+
+    Ok(ActionResponse {
+        serialized_output: json!({ "responses": responses }).to_string(),
+    })
+}
+
+// Simple trigger
+fn simple_trigger(context: TriggerContext) -> Result<TriggerResponse, AppError> {
+    let events = vec![TriggerEvent {
+        id: "event-1".to_string(),
+        serialized_data: json!({ "message": "Hello from v4.2 connector" }).to_string(),
+    }];
+
+    Ok(TriggerResponse {
+        store: context.store,
+        events,
+    })
+}
+
+export!(MyApp);
+
+// Actions implementation
+impl exports::standout::app::actions::Guest for MyApp {
+    fn action_ids() -> Result<Vec<String>, AppError> {
+        Ok(get_actions().iter().map(|a| a.id.to_string()).collect())
+    }
+
+    fn input_schema(context: ActionContext) -> Result<String, AppError> {
+        get_actions()
+            .iter()
+            .find(|a| a.id == context.action_id)
+            .map(|a| a.input_schema.to_string())
+            .ok_or_else(|| AppError {
+                code: ErrorCode::Misconfigured,
+                message: format!("Action '{}' not found", context.action_id),
+            })
+    }
+
+    fn output_schema(context: ActionContext) -> Result<String, AppError> {
+        get_actions()
+            .iter()
+            .find(|a| a.id == context.action_id)
+            .map(|a| a.output_schema.to_string())
+            .ok_or_else(|| AppError {
+                code: ErrorCode::Misconfigured,
+                message: format!("Action '{}' not found", context.action_id),
+            })
+    }
+
+    fn execute(context: ActionContext) -> Result<ActionResponse, AppError> {
+        get_actions()
+            .iter()
+            .find(|a| a.id == context.action_id)
+            .map(|a| (a.handler)(context.clone()))
+            .unwrap_or_else(|| {
+                Err(AppError {
+                    code: ErrorCode::Misconfigured,
+                    message: format!("Action '{}' not found", context.action_id),
+                })
+            })
+    }
+}
+
+// Triggers implementation
+impl exports::standout::app::triggers::Guest for MyApp {
+    fn trigger_ids() -> Result<Vec<String>, AppError> {
+        Ok(get_triggers().iter().map(|t| t.id.to_string()).collect())
+    }
+
+    fn input_schema(context: TriggerContext) -> Result<String, AppError> {
+        get_triggers()
+            .iter()
+            .find(|t| t.id == context.trigger_id)
+            .map(|t| t.input_schema.to_string())
+            .ok_or_else(|| AppError {
+                code: ErrorCode::Misconfigured,
+                message: format!("Trigger '{}' not found", context.trigger_id),
+            })
+    }
+
+    fn output_schema(context: TriggerContext) -> Result<String, AppError> {
+        get_triggers()
+            .iter()
+            .find(|t| t.id == context.trigger_id)
+            .map(|t| t.output_schema.to_string())
+            .ok_or_else(|| AppError {
+                code: ErrorCode::Misconfigured,
+                message: format!("Trigger '{}' not found", context.trigger_id),
+            })
+    }
+
+    fn fetch_events(context: TriggerContext) -> Result<TriggerResponse, AppError> {
+        get_triggers()
+            .iter()
+            .find(|t| t.id == context.trigger_id)
+            .map(|t| (t.handler)(context.clone()))
+            .unwrap_or_else(|| {
+                Err(AppError {
+                    code: ErrorCode::Misconfigured,
+                    message: format!("Trigger '{}' not found", context.trigger_id),
+                })
+            })
+    }
+}
@@ -3,6 +3,12 @@
 require "app_bridge"
 require "rspec-benchmark"
 require "json"
+require "tmpdir"
+require "fileutils"
+require "socket"
+require "weakref"
+
+Dir[File.join(__dir__, "support", "**", "*.rb")].each { |file| require file }
 
 RSpec.configure do |config|
   # Enable flags like --only-failures and --next-failure
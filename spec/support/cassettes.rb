@@ -0,0 +1,13 @@
+# frozen_string_literal: true
+
+# Locates recorded HTTP traffic in spec/fixtures/cassettes, for apps created
+# with +http_cassette:+.
+module Cassettes
+  def cassette_path(name)
+    File.expand_path(File.join("..", "fixtures", "cassettes", "#{name}.json"), __dir__)
+  end
+end
+
+RSpec.configure do |config|
+  config.include Cassettes
+end
@@ -0,0 +1,57 @@
+# frozen_string_literal: true
+
+require "socket"
+
+# Minimal HTTP server running on a Ruby thread, for specs that need a real
+# endpoint. Each request is answered by the given block, which receives the
+# request line, headers and body and returns [status, headers, body].
+class LocalHttpServer
+  Request = Struct.new(:method, :path, :headers, :body)
+
+  attr_reader :requests
+
+  def initialize(&handler)
+    @handler = handler || ->(_request) { [200, { "Content-Type" => "application/json" }, "{}"] }
+    @server = TCPServer.new("127.0.0.1", 0)
+    @requests = Queue.new
+    @thread = Thread.new { loop { serve(@server.accept) } }
+  end
+
+  def url(path = "/")
+    "http://127.0.0.1:#{@server.addr[1]}#{path}"
+  end
+
+  def stop
+    @thread.kill
+    @server.close
+  end
+
+  private
+
+  def serve(client)
+    request = read_request(client)
+    @requests << request
+    status, headers, body = @handler.call(request)
+    write_response(client, status, headers, body)
+  ensure
+    client.close
+  end
+
+  def read_request(client)
+    method, path = client.gets.split
+    headers = {}
+    while (line = client.gets) && line != "\r\n"
+      name, value = line.split(":", 2)
+      headers[name.downcase] = value.strip
+    end
+    Request.new(method, path, headers, client.read(headers.fetch("content-length", "0").to_i))
+  end
+
+  def write_response(client, status, headers, body)
+    client.write("HTTP/1.1 #{status} OK\r\n")
+    headers.merge("Content-Length" => body.bytesize, "Connection" => "close").each do |name, value|
+      client.write("#{name}: #{value}\r\n")
+    end
+    client.write("\r\n#{body}")
+  end
+end